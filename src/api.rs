@@ -3,7 +3,6 @@
 //! This module performs the single network call of the crate: a multipart `POST` of the serialized
 //! coverage report to the Coveralls `jobs` API.
 
-use crate::coverage::Coverage;
 use reqwest::{
     blocking::{
         Client,
@@ -14,7 +13,11 @@ use reqwest::{
 
 use simple_error::SimpleError;
 use log::{debug, error, info, warn};
-use std::io::{Result, Error, ErrorKind, Read};
+use std::{
+    io::{self, BufWriter, Result, Error, ErrorKind, Write},
+    thread,
+    time::Instant,
+};
 
 /// Convert a [`reqwest`] error into an [`std::io::Error`], returning early on failure.
 macro_rules! http_try {
@@ -28,24 +31,37 @@ macro_rules! http_try {
     };
 }
 
-/// Upload a serialized coverage report to <https://coveralls.io/api/v1/jobs>.
-///
-/// The report is sent as the `json_file` part of a multipart form. Returns an error when the
-/// request cannot be sent or when the API responds with anything other than `200 OK`.
-pub(super) fn send_to_api(coverage: &Coverage) -> Result<()> {
-    info!("Sending coverage to coveralls.io");
+/// Writer counting the bytes written through it.
+struct Counter<W> {
+    inner: W,
+    bytes: usize,
+}
 
-    let file = {
-        let mut buf = String::new();
-        let mut reader = coverage.new_reader()?;
+impl<W: Write> Write for Counter<W> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        let written = self.inner.write(buf)?;
 
-        reader.read_to_string(&mut buf)?;
-        buf
-    };
+        self.bytes += written;
 
-    debug!("Coverage payload is {} bytes", file.len());
+        Ok(written)
+    }
 
-    let part = http_try! { Part::text(file).file_name("json_file").mime_str("application/json") };
+    fn flush(&mut self) -> Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Upload a coverage report, serialized by `write_json`, to <https://coveralls.io/api/v1/jobs>.
+///
+/// The report is sent as the `json_file` part of a multipart form. It is serialized by a separate
+/// thread into a pipe read by the HTTP client, so the payload is never held in memory as a whole.
+/// Returns an error when the report cannot be serialized, when the request cannot be sent or when
+/// the API responds with anything other than `200 OK`.
+pub(super) fn send_to_api(write_json: impl FnOnce(&mut dyn Write) -> Result<()> + Send) -> Result<()> {
+    info!("Sending coverage to coveralls.io");
+
+    let (reader, writer) = io::pipe()?;
+    let part = http_try! { Part::reader(reader).file_name("json_file").mime_str("application/json") };
     let form = Form::new().part("json_file", part);
 
     let client = Client::new();
@@ -53,12 +69,44 @@ pub(super) fn send_to_api(coverage: &Coverage) -> Result<()> {
 
     debug!("POSTing coverage to {url}");
 
-    let req = client.post(url).multipart(form);
-    let resp = http_try! { req.send() };
+    let start = Instant::now();
+    let (resp, size) = thread::scope(|scope| {
+        let serializer = scope.spawn(move || {
+            let mut writer = BufWriter::new(Counter {
+                inner: writer,
+                bytes: 0,
+            });
+
+            write_json(&mut writer)?;
+
+            Ok(writer.get_ref().bytes)
+        });
+
+        let resp = client.post(url).multipart(form).send();
+        let size = serializer.join().unwrap_or_else(|_| {
+            Err(Error::other(SimpleError::new("The serialization of the payload panicked")))
+        });
+
+        (resp, size)
+    });
+
+    // A failed request closes the pipe, so its error comes first.
+    let resp = http_try! { resp };
+    let size = size?;
+
+    debug!("Coverage payload is {size} bytes");
+
     let status = resp.status();
+    let duration_ms = start.elapsed().as_millis();
 
     if status == StatusCode::OK {
-        info!("Coverage sent successfully");
+        info!(
+            stage = "upload",
+            status = status.as_u16(),
+            bytes = size,
+            duration_ms = duration_ms;
+            "Coverage sent successfully"
+        );
 
         match resp.text() {
             Ok(text) => {
@@ -77,7 +125,13 @@ pub(super) fn send_to_api(coverage: &Coverage) -> Result<()> {
             status.to_string()
         });
 
-        error!("Coveralls API rejected the upload (status {status}): {text}");
+        error!(
+            stage = "upload",
+            status = status.as_u16(),
+            bytes = size,
+            duration_ms = duration_ms;
+            "Coveralls API rejected the upload (status {status}): {text}"
+        );
 
         let msg = format!("API status {status}: {text}");
 
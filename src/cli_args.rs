@@ -5,7 +5,8 @@
 //! options common to all services. The long `after_help` strings document, for each service, the
 //! environment variables that are read.
 
-use clap::{Parser, Args, Subcommand};
+use crate::{coverage::MergeMode, git::{GitBackend, MessageFormat}, service::Service};
+use clap::{Parser, Args, Subcommand, ValueEnum};
 use std::path::PathBuf;
 
 /// Global command line arguments of the `coveralls` binary.
@@ -28,6 +29,10 @@ use std::path::PathBuf;
         Common environment variables:\n\
         - COVERALLS_REPO_TOKEN:    Coveralls repo token\n\
         - COVERALLS_FLAG_NAME:     Coveralls flag name\n\
+        - COVERALLS_ENV_FILE:      Dotenv file read when `--env-file` is not given\n\
+        - COVERALLS_SERVICE_ATTEMPT: Attempt number of the job\n\
+        - COVERALLS_COMPARE_REF:   Git reference the coverage is compared with\n\
+        - COVERALLS_COMPARE_SHA:   Git commit SHA the coverage is compared with\n\
         - GIT_ID:                  Git ID\n\
         - GIT_MESSAGE:             Git message\n\
         - GIT_AUTHOR_NAME:         Git author name\n\
@@ -49,6 +54,34 @@ pub(crate) struct CliArgs {
     #[clap(short = 'O', long, value_name = "file", global = true)]
     pub(crate) output: Option<PathBuf>,
 
+    /// Use `file` as output file for writing the processed coverage in the LCOV format
+    #[clap(long, value_name = "file", global = true)]
+    pub(crate) lcov_output: Option<PathBuf>,
+
+    /// Use `file` as output file for writing the processed coverage in the Cobertura XML format
+    #[clap(long, value_name = "file", global = true)]
+    pub(crate) cobertura_output: Option<PathBuf>,
+
+    /// Use `file` as output file for writing a Markdown summary of the coverage
+    #[clap(long, value_name = "file", global = true)]
+    pub(crate) summary_output: Option<PathBuf>,
+
+    /// Append the Markdown summary to `$GITHUB_STEP_SUMMARY` when running on GitHub Actions
+    #[clap(long, global = true)]
+    pub(crate) step_summary: bool,
+
+    /// Use `file`, a payload previously written with `--output`, as baseline for the summary deltas
+    #[clap(long, value_name = "file", global = true)]
+    pub(crate) summary_baseline: Option<PathBuf>,
+
+    /// Read environment variables from the dotenv file `file`; the process environment takes precedence
+    #[clap(short = 'E', long, value_name = "file", global = true)]
+    pub(crate) env_file: Option<PathBuf>,
+
+    /// Use the environment recorded in the snapshot `file` instead of the real one, and don't send to Coveralls
+    #[clap(long, value_name = "file", global = true)]
+    pub(crate) replay: Option<PathBuf>,
+
     /// Add a prefix to all files
     #[clap(short = 'P', long, value_name = "prefix", global = true)]
     pub(crate) source_prefix: Option<PathBuf>,
@@ -57,22 +90,86 @@ pub(crate) struct CliArgs {
     #[clap(short = 'D', long, value_name = "dir", global = true)]
     pub(crate) prune_dir: Option<Vec<PathBuf>>,
 
+    /// Search the Git repository from `dir` (or its parents) instead of the current directory
+    #[clap(long, value_name = "dir", global = true)]
+    pub(crate) repo_dir: Option<PathBuf>,
+
+    /// Collect the subject (`subject`) or the whole message (`full`) of the commit from Git
+    #[clap(long, value_name = "format", default_value = "subject", global = true)]
+    pub(crate) commit_message: MessageFormat,
+
+    /// Backend reading the Git repository: `auto` (the compiled in ones in turn, falling back on
+    /// error), `command`, `libgit`, `gitoxide` (when compiled in) or `none` (not read at all)
+    #[clap(long, value_name = "backend", default_value = "auto", global = true)]
+    pub(crate) git_backend: GitBackend,
+
+    /// Build the Git metadata from the environment and the command line only, without any access
+    /// to a Git repository (same as `--git-backend none`)
+    #[clap(long, global = true)]
+    pub(crate) offline: bool,
+
+    /// Only send the Git remote named `name` (can be repeated, every remote is sent by default)
+    #[clap(long, value_name = "name", global = true)]
+    pub(crate) keep_remote: Option<Vec<String>>,
+
+    /// Turn SSH and scp-like remote URLs (`git@host:owner/repo.git`) into HTTPS URLs
+    #[clap(long, global = true)]
+    pub(crate) canonical_remote_urls: bool,
+
     /// Force fetching of repository informations from Git
     #[clap(short = 'F', long, value_name = "dir", global = true)]
     pub(crate) force_fetch_git_infos: bool,
 
+    /// Merge the hit counts of duplicate source files by keeping the highest (`max`) or adding them up (`sum`)
+    #[clap(long, value_name = "mode", default_value = "max", global = true)]
+    pub(crate) merge_mode: MergeMode,
+
+    /// Drop the branch data of every source file (some tools report bogus branches for macros)
+    #[clap(long, global = true)]
+    pub(crate) no_branches: bool,
+
     /// Prune absolute paths
     #[clap(short = 'X', long, global = true)]
     pub(crate) prune_absolutes: bool,
 
+    /// Redact the matches of the regular expression `pattern` from the logs and the errors (can be
+    /// repeated; the repository token and the URL credentials are always redacted)
+    #[clap(long, value_name = "pattern", global = true)]
+    pub(crate) redact: Option<Vec<String>>,
+
+    /// Leave the repository token out of the payload written with `--output`
+    #[clap(long, global = true)]
+    pub(crate) omit_repo_token: bool,
+
     /// Don't send to Coveralls
     #[clap(short = 'z', long, global = true)]
     pub(crate) no_send: bool,
 
+    /// Don't check the processed report against the Coveralls job format
+    #[clap(long, global = true)]
+    pub(crate) skip_validation: bool,
+
+    /// Format of the log records written by the `coveralls` binary
+    #[clap(long, value_name = "format", value_enum, default_value_t = LogFormat::Text, global = true)]
+    pub(crate) log_format: LogFormat,
+
     #[clap(subcommand)]
     pub(crate) service: CliService,
 }
 
+/// Format of the log records, selected with `--log-format`.
+///
+/// It is handed to the logger initialization of [`work_with_logger`](crate::work_with_logger) once
+/// the arguments are parsed; [`run_with`](crate::run_with) ignores it, as its caller owns the logger.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum)]
+pub enum LogFormat {
+    /// Human-readable lines, as formatted by `env_logger`.
+    Text,
+
+    /// One JSON object per record, with the structured fields of the record as members.
+    Json,
+}
+
 /// CI service subcommand; selects the service and carries its [`CliServiceArgs`].
 #[derive(Subcommand)]
 pub(crate) enum CliService {
@@ -113,10 +210,16 @@ pub(crate) enum CliService {
         after_help = "\
         Used environment variables for GitHub Actions:\n\
         - GITHUB_REF, GITHUB_HEAD_REF: Git branch\n\
+        - GITHUB_SHA:                  Git ID\n\
         - GITHUB_RUN_ID:               Service number\n\
+        - GITHUB_SERVER_URL, GITHUB_REPOSITORY, GITHUB_RUN_ID: Service build URL\n\
         - GITHUB_REF:                  Service pull request\n\
         - GITHUB_JOB:                  Service job id\n\
         - GITHUB_RUN_NUMBER:           Service job number\n\
+        - GITHUB_RUN_ATTEMPT:          Service attempt\n\
+        - GITHUB_EVENT_PATH:           Event payload, whose pull request gives the service pull\n\
+                                       request, the Git ID and branch (the head of the pull\n\
+                                       request) and the compare ref and SHA (its base)\n\
         \n\
         Common environment variables:\n\
         - GIT_ID:                  Git ID\n\
@@ -134,6 +237,21 @@ pub(crate) enum CliService {
     )]
     Actions(CliServiceArgs),
 
+    /// Service GitHub Actions, authenticated with the GITHUB_TOKEN of the workflow
+    #[clap(
+        name = "github",
+        after_help = "\
+        Same environment variables as the `actions` subcommand, plus:\n\
+        - GITHUB_TOKEN:                Repository token, when COVERALLS_REPO_TOKEN is not set\n\
+        - GITHUB_RUN_ID:               Service job id\n\
+        \n\
+        The workflow must expose the token, for instance with\n\
+        `env: GITHUB_TOKEN: ${{ secrets.GITHUB_TOKEN }}`.\n\
+        \n\
+    "
+    )]
+    Github(CliServiceArgs),
+
     /// Service AppVeyor
     #[clap(
         name = "appveyor",
@@ -287,6 +405,72 @@ pub(crate) enum CliService {
     )]
     Jenkins(CliServiceArgs),
 
+    /// Compare the coverage report with a baseline report, without sending anything
+    #[clap(
+        name = "compare",
+        after_help = "\
+        The report read from `file_name` (or from the standard input) is compared with the baseline \
+        report, typically a payload previously written with `--output` on the main branch. Files are \
+        aligned by name, then by digest to detect renamed files.\n\
+        \n\
+        With `--fail-on-regression`, the command fails when the total coverage dropped by more than \
+        `--max-decrease` percentage points, or when some lines went from covered to uncovered.\n\
+        \n\
+    "
+    )]
+    Compare(CliCompareArgs),
+
+    /// Check the coverage report against the Coveralls job format, without sending anything
+    #[clap(
+        name = "validate",
+        after_help = "\
+        The report read from `file_name` (or from the standard input) is checked for structural and \
+        semantic issues that the Coveralls API would reject: negative hit counts, `branches` arrays \
+        whose length is not a multiple of 4 or referring to unknown lines, empty names or digests, \
+        duplicate files, ... Every issue is printed with the index and name of the source file, and \
+        the command fails when there is any.\n\
+        \n\
+        The same checks run before every upload, unless `--skip-validation` is given.\n\
+        \n\
+    "
+    )]
+    Validate,
+
+    /// Record the CI environment into a JSON snapshot, to be replayed with `--replay`
+    #[clap(
+        name = "snapshot",
+        after_help = "\
+        Every environment variable that the service detection or any service loader reads, and that is \
+        set, is recorded. The secrets (COVERALLS_REPO_TOKEN and GITHUB_TOKEN) are masked. On GitHub \
+        Actions, the pull request of the event payload (GITHUB_EVENT_PATH) is embedded too, with any \
+        string containing a secret masked the same way: a branch named after a token is replayed \
+        masked. The snapshot is written to the file given with `--output`, or to the standard output.\n\
+        \n\
+        Replay it anywhere with `--replay <file>`: the snapshot then replaces the whole environment, and \
+        nothing is sent to Coveralls.\n\
+        \n\
+    "
+    )]
+    Snapshot,
+
+    /// Explain where every configuration value comes from, without sending anything
+    #[clap(
+        name = "doctor",
+        visible_alias = "explain",
+        after_help = "\
+        The configuration is resolved as for a real upload, either for the service given with `--service` \
+        or, by default, from the environment as with `env`; the options of the service subcommands are \
+        accepted and override the environment. For every field, the report tells whether it comes from a \
+        command line flag, from which environment variable, from the local Git repository or from the \
+        input report, and which rule detected the service. Suspicious combinations are reported as \
+        warnings.\n\
+        \n\
+        The report is written to the file given with `--output`, or to the standard output.\n\
+        \n\
+    "
+    )]
+    Doctor(CliDoctorArgs),
+
     /// Guess service from environment
     #[clap(
         name = "env",
@@ -395,6 +579,18 @@ pub(crate) struct CliServiceArgs {
     #[clap(short = 'r', long, value_name = "name")]
     pub(crate) service_repo_name: Option<String>,
 
+    /// Service attempt number, when the job is re-run
+    #[clap(long, value_name = "attempt")]
+    pub(crate) service_attempt: Option<String>,
+
+    /// Git reference the coverage is compared with
+    #[clap(long, value_name = "ref")]
+    pub(crate) compare_ref: Option<String>,
+
+    /// Git commit SHA the coverage is compared with
+    #[clap(long, value_name = "sha")]
+    pub(crate) compare_sha: Option<String>,
+
     /// Git ID
     #[clap(short = 'K', long, value_name = "id")]
     pub(crate) git_id: Option<String>,
@@ -435,3 +631,36 @@ pub(crate) struct CliServiceArgs {
     #[clap(short = 'R', long, value_name = "url")]
     pub(crate) git_remote_url: Option<String>,
 }
+
+/// Options of the `compare` subcommand.
+#[derive(Args)]
+pub(crate) struct CliCompareArgs {
+    /// Baseline report to compare with
+    #[clap(short = 'b', long, value_name = "file")]
+    pub(crate) baseline: PathBuf,
+
+    /// Fail when the coverage regressed against the baseline
+    #[clap(short = 'g', long)]
+    pub(crate) fail_on_regression: bool,
+
+    /// Tolerated decrease of the total coverage, in percentage points
+    #[clap(short = 'm', long, value_name = "percent", default_value_t = 0.0)]
+    pub(crate) max_decrease: f64,
+}
+
+/// Arguments of the `doctor` subcommand.
+#[derive(Args)]
+pub(crate) struct CliDoctorArgs {
+    /// Explain the configuration of this service (as named by Coveralls, i.e.: `github-actions`)
+    /// instead of guessing it from the environment
+    #[clap(short = 'S', long, value_name = "name", value_parser = parse_service)]
+    pub(crate) service: Option<Service>,
+
+    #[clap(flatten)]
+    pub(crate) args: CliServiceArgs,
+}
+
+/// Parse a Coveralls service name.
+fn parse_service(name: &str) -> Result<Service, String> {
+    Service::from_name(name).ok_or_else(|| format!("unknown service `{name}`"))
+}
@@ -0,0 +1,357 @@
+//! Comparison of a coverage report against a baseline report.
+//!
+//! [`Comparison::new`] aligns the source files of two [`Coverage`] reports, first by name and then,
+//! for the leftovers, by digest (a renamed file keeps its digest, a digest shared by several files
+//! being ambiguous matches nothing), and computes per-file and total coverage deltas. For files
+//! whose content did not change, the lines that went from covered to uncovered are listed as well.
+
+use crate::{
+    coverage::{Coverage, SourceFile},
+    helpers::percent,
+};
+
+use itertools::Itertools;
+use log::debug;
+use std::{collections::HashMap, fmt};
+
+/// How a source file evolved between the baseline and the current report.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum FileStatus {
+    /// The file only exists in the current report.
+    Added,
+
+    /// The file only exists in the baseline report.
+    Removed,
+
+    /// The file exists in both reports with a different digest.
+    Modified,
+
+    /// The file exists in both reports with the same name and digest.
+    Unchanged,
+
+    /// The file exists in both reports with the same digest, under a different name.
+    Renamed,
+}
+
+impl FileStatus {
+    /// Short label used in the text report.
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Modified => "modified",
+            Self::Unchanged => "unchanged",
+            Self::Renamed => "renamed",
+        }
+    }
+}
+
+/// Line counts of a source file in one of the compared reports (or branch counts of a whole report).
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+struct LineCounts {
+    valid:   usize,
+    covered: usize,
+}
+
+impl LineCounts {
+    /// Counts of a single source file.
+    fn of(source: &SourceFile) -> Self {
+        Self {
+            valid:   source.lines_valid(),
+            covered: source.lines_covered(),
+        }
+    }
+
+    /// Coverage percentage.
+    fn percent(&self) -> f64 {
+        percent(self.covered, self.valid)
+    }
+}
+
+/// Comparison of a single source file between the baseline and the current report.
+#[derive(Debug)]
+pub struct FileComparison {
+    name:            String,
+    previous_name:   Option<String>,
+    status:          FileStatus,
+    baseline:        Option<LineCounts>,
+    current:         Option<LineCounts>,
+    regressed_lines: Vec<usize>,
+}
+
+impl FileComparison {
+    /// Compare two entries of the same file, `old` coming from the baseline.
+    fn matched(old: &SourceFile, new: &SourceFile) -> Self {
+        let status = if old.source_digest != new.source_digest {
+            FileStatus::Modified
+        } else if old.name != new.name {
+            FileStatus::Renamed
+        } else {
+            FileStatus::Unchanged
+        };
+
+        // Line numbers are only meaningful across reports when the content is the same.
+        let regressed_lines = if status == FileStatus::Modified {
+            vec![]
+        } else {
+            old.coverage
+                .iter()
+                .zip(new.coverage.iter())
+                .enumerate()
+                .filter(|(_, (old, new))| matches!((old, new), (Some(o), Some(0)) if *o > 0))
+                .map(|(idx, _)| idx + 1)
+                .collect()
+        };
+
+        Self {
+            name: new.name.clone(),
+            previous_name: (old.name != new.name).then(|| old.name.clone()),
+            status,
+            baseline: Some(LineCounts::of(old)),
+            current: Some(LineCounts::of(new)),
+            regressed_lines,
+        }
+    }
+
+    /// The name of the file (in the current report, or in the baseline for a removed file).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The name of the file in the baseline, when it was renamed.
+    pub fn previous_name(&self) -> Option<&str> {
+        self.previous_name.as_deref()
+    }
+
+    /// How the file evolved between the two reports.
+    pub fn status(&self) -> FileStatus {
+        self.status
+    }
+
+    /// Line coverage percentage in the baseline report, if the file is present there.
+    pub fn baseline_percent(&self) -> Option<f64> {
+        self.baseline.as_ref().map(LineCounts::percent)
+    }
+
+    /// Line coverage percentage in the current report, if the file is present there.
+    pub fn current_percent(&self) -> Option<f64> {
+        self.current.as_ref().map(LineCounts::percent)
+    }
+
+    /// Coverage percentage difference, when the file is present in both reports.
+    pub fn delta(&self) -> Option<f64> {
+        Some(self.current_percent()? - self.baseline_percent()?)
+    }
+
+    /// The (1-based) lines that were covered in the baseline and are not covered anymore.
+    ///
+    /// Always empty for a [`FileStatus::Modified`] file, since its lines cannot be aligned.
+    pub fn regressed_lines(&self) -> &[usize] {
+        &self.regressed_lines
+    }
+}
+
+/// Comparison of a coverage report against a baseline report.
+#[derive(Debug)]
+pub struct Comparison {
+    baseline:          LineCounts,
+    current:           LineCounts,
+    baseline_branches: LineCounts,
+    current_branches:  LineCounts,
+    files:             Vec<FileComparison>,
+}
+
+impl Comparison {
+    /// Compare the `current` report against the `baseline` one.
+    pub fn new(baseline: &Coverage, current: &Coverage) -> Self {
+        let mut unmatched = baseline
+            .source_files
+            .iter()
+            .map(|source| (source.name.as_str(), source))
+            .collect::<HashMap<_, _>>();
+
+        let mut files = vec![];
+        let mut added = vec![];
+
+        for source in &current.source_files {
+            match unmatched.remove(source.name.as_str()) {
+                Some(old) => files.push(FileComparison::matched(old, source)),
+                None => added.push(source),
+            }
+        }
+
+        // Identical files (empty modules, generated stubs, ...) share their digest: a rename is only
+        // recorded when the digest designates a single file on both sides.
+        let mut by_digest = HashMap::<_, Vec<_>>::new();
+        let mut added_digests = HashMap::<_, usize>::new();
+
+        for source in unmatched.values() {
+            by_digest.entry(source.source_digest.as_str()).or_default().push(*source);
+        }
+
+        for source in &added {
+            *added_digests.entry(source.source_digest.as_str()).or_default() += 1;
+        }
+
+        for source in added {
+            let digest = source.source_digest.as_str();
+            let renamed = match by_digest.get(digest) {
+                Some(olds) if olds.len() == 1 && added_digests[digest] == 1 => Some(olds[0]),
+                _ => None,
+            };
+
+            match renamed {
+                Some(old) => {
+                    unmatched.remove(old.name.as_str());
+                    files.push(FileComparison::matched(old, source));
+                }
+                None => files.push(FileComparison {
+                    name:            source.name.clone(),
+                    previous_name:   None,
+                    status:          FileStatus::Added,
+                    baseline:        None,
+                    current:         Some(LineCounts::of(source)),
+                    regressed_lines: vec![],
+                }),
+            }
+        }
+
+        files.extend(unmatched.into_values().map(|source| FileComparison {
+            name:            source.name.clone(),
+            previous_name:   None,
+            status:          FileStatus::Removed,
+            baseline:        Some(LineCounts::of(source)),
+            current:         None,
+            regressed_lines: vec![],
+        }));
+
+        files.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let totals = |coverage: &Coverage| {
+            let (valid, covered) = coverage.line_totals();
+
+            LineCounts {
+                valid,
+                covered,
+            }
+        };
+
+        let branch_totals = |coverage: &Coverage| {
+            let (valid, covered) = coverage.branch_totals();
+
+            LineCounts {
+                valid,
+                covered,
+            }
+        };
+
+        debug!("Compared {} source file(s) against the baseline", files.len());
+
+        Self {
+            baseline: totals(baseline),
+            current: totals(current),
+            baseline_branches: branch_totals(baseline),
+            current_branches: branch_totals(current),
+            files,
+        }
+    }
+
+    /// Total line coverage percentage of the baseline report.
+    pub fn baseline_percent(&self) -> f64 {
+        self.baseline.percent()
+    }
+
+    /// Total line coverage percentage of the current report.
+    pub fn current_percent(&self) -> f64 {
+        self.current.percent()
+    }
+
+    /// Total coverage percentage difference.
+    pub fn delta(&self) -> f64 {
+        self.current_percent() - self.baseline_percent()
+    }
+
+    /// Per-file comparisons, sorted by name.
+    pub fn files(&self) -> &[FileComparison] {
+        &self.files
+    }
+
+    /// Total number of lines that went from covered to uncovered.
+    pub fn regressed_lines(&self) -> usize {
+        self.files.iter().map(|file| file.regressed_lines.len()).sum()
+    }
+
+    /// Tell whether the current report regressed: the total coverage dropped by more than
+    /// `max_decrease` percentage points, or some lines went from covered to uncovered.
+    pub fn is_regression(&self, max_decrease: f64) -> bool {
+        self.delta() < -max_decrease || self.regressed_lines() > 0
+    }
+}
+
+/// Render an optional percentage, or a dash when the file is absent from a report.
+fn show_percent(value: Option<f64>) -> String {
+    value.map(|v| format!("{v:.2}%")).unwrap_or_else(|| String::from("-"))
+}
+
+impl fmt::Display for Comparison {
+    /// Render a human-readable comparison report, listing only the files that changed.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Total coverage: {:.2}% -> {:.2}% ({:+.2}%)",
+            self.baseline_percent(),
+            self.current_percent(),
+            self.delta()
+        )?;
+
+        if self.baseline_branches.valid > 0 || self.current_branches.valid > 0 {
+            writeln!(
+                f,
+                "Branch coverage: {:.2}% ({}/{}) -> {:.2}% ({}/{})",
+                self.baseline_branches.percent(),
+                self.baseline_branches.covered,
+                self.baseline_branches.valid,
+                self.current_branches.percent(),
+                self.current_branches.covered,
+                self.current_branches.valid
+            )?;
+        }
+
+        for file in &self.files {
+            let unchanged = file.status == FileStatus::Unchanged && file.baseline == file.current;
+
+            if unchanged && file.regressed_lines.is_empty() {
+                continue;
+            }
+
+            write!(
+                f,
+                "{:<9} {}: {} -> {}",
+                file.status.label(),
+                file.name,
+                show_percent(file.baseline_percent()),
+                show_percent(file.current_percent()),
+            )?;
+
+            if let Some(delta) = file.delta() {
+                write!(f, " ({delta:+.2}%)")?;
+            }
+
+            if let Some(previous) = &file.previous_name {
+                write!(f, " [was {previous}]")?;
+            }
+
+            writeln!(f)?;
+
+            if !file.regressed_lines.is_empty() {
+                writeln!(
+                    f,
+                    "          lines no longer covered: {}",
+                    file.regressed_lines.iter().join(", ")
+                )?;
+            }
+        }
+
+        Ok(())
+    }
+}
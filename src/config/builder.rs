@@ -0,0 +1,154 @@
+//! Programmatic construction of a [`Config`], for library users that do not go through the command
+//! line or the environment.
+
+use super::Config;
+use crate::{coverage::MergeMode, git::{GitBackend, MessageFormat}};
+use std::path::PathBuf;
+
+/// Generate the chainable setters of optional string fields.
+macro_rules! setters {
+    ($($(#[$doc:meta])* $name:ident;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(mut self, value: impl Into<String>) -> Self {
+                self.config.$name.replace(value.into());
+                self
+            }
+        )*
+    };
+}
+
+/// Builder of a [`Config`], obtained with [`Config::builder`].
+///
+/// Every value is unset until its setter is called; nothing is read from the environment.
+pub struct ConfigBuilder {
+    config: Config,
+}
+
+impl ConfigBuilder {
+    setters! {
+        /// Set the Coveralls flag name.
+        flag_name;
+        /// Set the Coveralls repository token.
+        repo_token;
+        /// Set the service project ID.
+        service_project_id;
+        /// Set the service build ID.
+        service_build_id;
+        /// Set the service build version.
+        service_build_version;
+        /// Set the service build number.
+        service_build_number;
+        /// Set the service build URL.
+        service_build_url;
+        /// Set the service pull request.
+        service_pull_request;
+        /// Set the service job ID.
+        service_job_id;
+        /// Set the service job name.
+        service_job_name;
+        /// Set the service job number.
+        service_job_number;
+        /// Set the service repository name.
+        service_repo_name;
+        /// Set the attempt number of the job.
+        service_attempt;
+        /// Set the Git reference the coverage is compared with.
+        compare_ref;
+        /// Set the Git commit SHA the coverage is compared with.
+        compare_sha;
+        /// Set the Git commit ID.
+        git_id;
+        /// Set the Git tag.
+        git_tag;
+        /// Set the Git branch.
+        git_branch;
+        /// Set the Git commit message.
+        git_message;
+        /// Set the Git author name.
+        git_author_name;
+        /// Set the Git author email.
+        git_author_email;
+        /// Set the Git committer name.
+        git_committer_name;
+        /// Set the Git committer email.
+        git_committer_email;
+        /// Set the Git remote name.
+        git_remote_name;
+        /// Set the Git remote URL.
+        git_remote_url;
+    }
+
+    /// Wrap a configuration to be completed.
+    pub(super) fn new(config: Config) -> Self {
+        Self {
+            config,
+        }
+    }
+
+    /// Prune the source files whose path is absolute.
+    pub fn prune_absolutes(mut self, value: bool) -> Self {
+        self.config.param_prune_absolutes = value;
+        self
+    }
+
+    /// Prune the source files under `dir`; can be called several times.
+    pub fn prune_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.param_prune_dirs.push(dir.into());
+        self
+    }
+
+    /// Prepend `prefix` to the name of every kept source file.
+    pub fn source_prefix(mut self, prefix: impl Into<PathBuf>) -> Self {
+        self.config.param_src_prefix.replace(prefix.into());
+        self
+    }
+
+    /// Merge the hit counts of duplicate source files according to `mode`.
+    pub fn merge_mode(mut self, mode: MergeMode) -> Self {
+        self.config.param_merge_mode = mode;
+        self
+    }
+
+    /// Drop the branch data of every source file.
+    pub fn drop_branches(mut self, value: bool) -> Self {
+        self.config.param_drop_branches = value;
+        self
+    }
+
+    /// Search the Git repository from `dir` instead of the current directory.
+    pub fn repo_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.config.param_repo_dir.replace(dir.into());
+        self
+    }
+
+    /// Collect the part of the commit message selected by `format` from the repository.
+    pub fn message_format(mut self, format: MessageFormat) -> Self {
+        self.config.param_message_format = format;
+        self
+    }
+
+    /// Only send the Git remote named `name` (can be called several times); every remote is sent
+    /// by default.
+    pub fn keep_remote(mut self, name: impl Into<String>) -> Self {
+        self.config.param_keep_remotes.push(name.into());
+        self
+    }
+
+    /// Turn the SSH and scp-like remote URLs into HTTPS URLs.
+    pub fn canonical_remote_urls(mut self, value: bool) -> Self {
+        self.config.param_canonical_remotes = value;
+        self
+    }
+
+    /// Read the local Git repository with `backend`.
+    pub fn git_backend(mut self, backend: GitBackend) -> Self {
+        self.config.param_git_backend = backend;
+        self
+    }
+
+    /// Return the built configuration.
+    pub fn build(self) -> Config {
+        self.config
+    }
+}
@@ -0,0 +1,505 @@
+//! Explanation of a resolved configuration: where every value comes from.
+//!
+//! The configuration is loaded exactly as for an upload, but every loader runs against a recording
+//! [`Env`], so that each value can be traced back to the environment variable that set it. Command
+//! line flags, the local Git repository and the input report are accounted for on top of that.
+
+use super::{Config, Detection, Loader};
+use crate::{
+    cli_args::{CliArgs, CliDoctorArgs},
+    coverage::{Coverage, MergeMode},
+    env::Env,
+    git::{GitBackend, GitInfos, MessageFormat, sanitize_url},
+    helpers,
+    service::Service,
+};
+
+use log::debug;
+use std::{collections::HashMap, fmt, fs::File, io::Result};
+
+/// Generate the table of the string fields of a configuration.
+macro_rules! fields {
+    ($($name:ident => $flag:literal,)*) => {
+        /// Names of the string fields of a configuration, with the command line flag setting each.
+        const FIELDS: &[(&str, &str)] = &[$((stringify!($name), $flag)),*];
+
+        /// Values of the string fields of `config`, in the order of [`FIELDS`].
+        fn values(config: &Config) -> Vec<Option<String>> {
+            vec![$(config.$name.clone()),*]
+        }
+    };
+}
+
+fields! {
+    repo_token => "--repo-token",
+    flag_name => "--flag-name",
+    service_project_id => "--project-id",
+    service_repo_name => "--service-repo-name",
+    service_build_id => "--service-build-id",
+    service_build_number => "--service-build-number",
+    service_build_version => "--service-build-version",
+    service_build_url => "--service-build-url",
+    service_pull_request => "--service-pull-request",
+    service_job_id => "--service-job-id",
+    service_job_name => "--service-job-name",
+    service_job_number => "--service-job-number",
+    service_attempt => "--service-attempt",
+    compare_ref => "--compare-ref",
+    compare_sha => "--compare-sha",
+    git_id => "--git-id",
+    git_branch => "--git-branch",
+    git_tag => "--git-tag",
+    git_message => "--git-message",
+    git_author_name => "--git-author-name",
+    git_author_email => "--git-author-email",
+    git_committer_name => "--git-committer-name",
+    git_committer_email => "--git-committer-email",
+    git_remote_name => "--git-remote-name",
+    git_remote_url => "--git-remote-url",
+}
+
+/// Where the value of a configuration field comes from.
+enum Origin {
+    /// The field is not set.
+    Unset,
+
+    /// The field keeps its default value.
+    Default,
+
+    /// A command line flag.
+    Flag(&'static str),
+
+    /// An environment variable.
+    Variable(String),
+
+    /// The local Git repository.
+    Repository,
+
+    /// The input coverage report.
+    Report,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unset => write!(f, "unset"),
+            Self::Default => write!(f, "default"),
+            Self::Flag(flag) => write!(f, "flag `{flag}`"),
+            Self::Variable(name) => write!(f, "environment variable `{name}`"),
+            Self::Repository => write!(f, "local Git repository"),
+            Self::Report => write!(f, "input report"),
+        }
+    }
+}
+
+/// A resolved configuration field and its origin.
+struct Field {
+    name:   &'static str,
+    value:  Option<String>,
+    origin: Origin,
+}
+
+/// The value of the field `name` as read from the local Git repository.
+fn repository_value<'a>(name: &str, git: &'a GitInfos) -> Option<&'a str> {
+    let value = match name {
+        "git_id" => &git.head.id,
+        "git_branch" => &git.branch,
+        "git_message" => &git.head.message,
+        "git_author_name" => &git.head.author_name,
+        "git_author_email" => &git.head.author_email,
+        "git_committer_name" => &git.head.committer_name,
+        "git_committer_email" => &git.head.committer_email,
+        "git_remote_name" => &git.remotes.first()?.name,
+        "git_remote_url" => &git.remotes.first()?.url,
+        _ => return None,
+    };
+
+    (!value.is_empty()).then_some(value.as_str())
+}
+
+/// The value of the field `name` as carried by the input report.
+fn report_value<'a>(name: &str, coverage: &'a Coverage) -> Option<&'a str> {
+    let value = match name {
+        "repo_token" => &coverage.repo_token,
+        "flag_name" => coverage.flag_name.as_ref()?,
+        "service_build_number" => &coverage.service_number,
+        "service_pull_request" => &coverage.service_pull_request,
+        "service_job_id" => &coverage.service_job_id,
+        _ => return repository_value(name, coverage.git.as_ref()?),
+    };
+
+    (!value.is_empty()).then_some(value.as_str())
+}
+
+/// Find the variable, among those `read` by `loader`, that set the field at `idx` to `value`.
+///
+/// Each variable is replayed alone through the loader; the last one (in reading order) producing
+/// `value` wins, as it would in the real loader. Variables producing another value are a fallback,
+/// for values derived from several variables.
+fn attribute(
+    loader: Loader,
+    service: Service,
+    idx: usize,
+    value: &str,
+    read: &[String],
+    env: &Env,
+) -> Result<Option<String>> {
+    let mut fallback = None;
+
+    for name in read.iter().rev() {
+        let Some(var) = env.get_var(name)? else {
+            continue;
+        };
+
+        let mut isolated = Config::blank(service);
+
+        loader(&mut isolated, &Env::with_source(HashMap::from([(name.clone(), var)])))?;
+
+        match &values(&isolated)[idx] {
+            Some(v) if v == value => return Ok(Some(name.clone())),
+            Some(_) => {
+                fallback.get_or_insert_with(|| name.clone());
+            }
+            None => {}
+        }
+    }
+
+    Ok(fallback)
+}
+
+/// Explanation of the configuration a run would use: the rule that detected the service, the
+/// origin of every field, and warnings about suspicious combinations.
+pub(crate) struct Diagnosis {
+    rule:     String,
+    service:  Option<Service>,
+    fields:   Vec<Field>,
+    warnings: Vec<String>,
+}
+
+impl Diagnosis {
+    /// Resolve the configuration as the `doctor` subcommand arguments designate it, and explain it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the service named in the environment is not implemented, if
+    /// the input report cannot be read, or if an environment variable holds non-Unicode data.
+    pub(crate) fn new(args: &CliArgs, doctor: &CliDoctorArgs, env: Env) -> Result<Diagnosis> {
+        let (env, log) = env.record();
+        let detection = match doctor.service {
+            Some(service) => Some(Detection::Command(service)),
+            None => Detection::from_env(&env)?,
+        };
+
+        let Some(detection) = detection else {
+            return Ok(Diagnosis {
+                rule:     String::from("none (CI_NAME, COVERALLS_SERVICE_NAME and the marker variables are unset)"),
+                service:  None,
+                fields:   vec![],
+                warnings: vec![String::from(
+                    "No CI service detected: pass `--service`, or set CI_NAME or COVERALLS_SERVICE_NAME",
+                )],
+            });
+        };
+
+        let rule = match &detection {
+            Detection::Command(_) => String::from("`--service` option, with the native variables of the service"),
+            Detection::CiName(_) => format!(
+                "CI_NAME=`{}`, with the generic CI_* variables",
+                env.get_var("CI_NAME")?.unwrap_or_default()
+            ),
+            Detection::CoverallsServiceName(_) => format!(
+                "COVERALLS_SERVICE_NAME=`{}`, with the COVERALLS_* variables",
+                env.get_var("COVERALLS_SERVICE_NAME")?.unwrap_or_default()
+            ),
+            Detection::Marker(_) => format!(
+                "marker variable `{}`, with the native variables of the service",
+                log.names_since(0).last().cloned().unwrap_or_default()
+            ),
+        };
+
+        let service = detection.service();
+        let mut config = Config::blank(service);
+        let mut origins = FIELDS.iter().map(|_| Origin::Unset).collect::<Vec<_>>();
+
+        for loader in detection.loaders() {
+            let start = log.len();
+
+            loader(&mut config, &env)?;
+
+            let read = log.names_since(start);
+
+            for (idx, value) in values(&config).iter().enumerate() {
+                if let Some(value) = value
+                    && let Some(name) = attribute(loader, service, idx, value, &read, &env)?
+                {
+                    origins[idx] = Origin::Variable(name);
+                }
+            }
+        }
+
+        let mut flags = Config::blank(service);
+
+        flags.configure(&doctor.args);
+        config.configure(&doctor.args);
+
+        for (idx, value) in values(&flags).iter().enumerate() {
+            if value.is_some() {
+                origins[idx] = Origin::Flag(FIELDS[idx].1);
+            }
+        }
+
+        let config = config.init_parameters(args);
+        let mut warnings = vec![];
+
+        let report = match &args.input {
+            Some(input) => Some(Coverage::from_reader(File::open(input)?)?),
+            None => {
+                debug!("No input report given, values it may carry are not accounted for");
+
+                None
+            }
+        };
+
+        let repository = match GitInfos::from_repository(&config) {
+            Ok(v) => Some(v),
+            Err(err) => {
+                warnings.push(format!("The local Git repository cannot be read: {err}"));
+
+                None
+            }
+        };
+
+        let mut fields = vec![];
+
+        for (((name, _), value), origin) in FIELDS.iter().zip(values(&config)).zip(origins) {
+            let fallback = || {
+                if let Some(v) = repository.as_ref().and_then(|git| repository_value(name, git)) {
+                    return (Some(v.to_string()), Origin::Repository);
+                }
+
+                if let Some(v) = report.as_ref().and_then(|coverage| report_value(name, coverage)) {
+                    return (Some(v.to_string()), Origin::Report);
+                }
+
+                (None, Origin::Unset)
+            };
+
+            let (value, origin) = match value {
+                Some(v) => (Some(v), origin),
+                None => fallback(),
+            };
+
+            fields.push(Field {
+                name,
+                value,
+                origin,
+            });
+        }
+
+        // The commit details that are not sent to Coveralls, only read from the repository.
+        if let Some(git) = &repository {
+            let head = git.head();
+            let details = [
+                ("git_author_date", head.author_date().to_string()),
+                ("git_committer_date", head.committer_date().to_string()),
+                ("git_parents", head.parents().join(", ")),
+                ("git_signature", head.signature().map(|v| format!("{v:?}").to_lowercase()).unwrap_or_default()),
+            ];
+
+            for (name, value) in details {
+                fields.push(Field {
+                    name,
+                    origin: if value.is_empty() { Origin::Unset } else { Origin::Repository },
+                    value: (!value.is_empty()).then_some(value),
+                });
+            }
+        }
+
+        let value = |name: &str| {
+            fields
+                .iter()
+                .find(|field| field.name == name)
+                .and_then(|field| field.value.clone())
+        };
+
+        if value("repo_token").is_none() {
+            warnings.push(String::from(if service == Service::Github {
+                "No repository token: set GITHUB_TOKEN or COVERALLS_REPO_TOKEN, or pass `--repo-token`"
+            } else {
+                "No repository token: set COVERALLS_REPO_TOKEN, or pass `--repo-token`"
+            }));
+        }
+
+        if let Some(pull_request) = value("service_pull_request")
+            && value("git_branch").is_none()
+        {
+            warnings.push(format!(
+                "Pull request `{pull_request}` without a branch: Coveralls cannot attach the job to it"
+            ));
+        }
+
+        if matches!(env.get_var("COVERALLS_PARALLEL")?.as_deref(), Some("true" | "1"))
+            && value("service_job_id").is_none()
+        {
+            warnings.push(String::from(
+                "COVERALLS_PARALLEL is set but no job ID: the jobs of a parallel build cannot be told apart",
+            ));
+        }
+
+        if config.git_remote_name.is_some() != config.git_remote_url.is_some() {
+            warnings.push(String::from(
+                "Only one of the Git remote name and URL is configured: both are needed, it is ignored",
+            ));
+        }
+
+        fields.push(Field {
+            name:   "prune_absolutes",
+            value:  Some(config.param_prune_absolutes.to_string()),
+            origin: if args.prune_absolutes {
+                Origin::Flag("--prune-absolutes")
+            } else {
+                Origin::Default
+            },
+        });
+
+        fields.push(Field {
+            name:   "prune_dirs",
+            value:  Some(
+                config
+                    .param_prune_dirs
+                    .iter()
+                    .map(helpers::path_to_string)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ),
+            origin: if args.prune_dir.is_some() {
+                Origin::Flag("--prune-dir")
+            } else {
+                Origin::Default
+            },
+        });
+
+        fields.push(Field {
+            name:   "merge_mode",
+            value:  Some(format!("{:?}", config.param_merge_mode).to_lowercase()),
+            origin: if args.merge_mode != MergeMode::default() {
+                Origin::Flag("--merge-mode")
+            } else {
+                Origin::Default
+            },
+        });
+
+        fields.push(Field {
+            name:   "drop_branches",
+            value:  Some(config.param_drop_branches.to_string()),
+            origin: if args.no_branches {
+                Origin::Flag("--no-branches")
+            } else {
+                Origin::Default
+            },
+        });
+
+        fields.push(Field {
+            name:   "message_format",
+            value:  Some(format!("{:?}", config.param_message_format).to_lowercase()),
+            origin: if args.commit_message != MessageFormat::default() {
+                Origin::Flag("--commit-message")
+            } else {
+                Origin::Default
+            },
+        });
+
+        fields.push(Field {
+            name:   "git_backend",
+            value:  Some(config.param_git_backend.name().to_string()),
+            origin: if args.offline {
+                Origin::Flag("--offline")
+            } else if args.git_backend != GitBackend::default() {
+                Origin::Flag("--git-backend")
+            } else {
+                Origin::Default
+            },
+        });
+
+        fields.push(Field {
+            name:   "keep_remotes",
+            value:  (!config.param_keep_remotes.is_empty()).then(|| config.param_keep_remotes.join(", ")),
+            origin: if args.keep_remote.is_some() {
+                Origin::Flag("--keep-remote")
+            } else {
+                Origin::Unset
+            },
+        });
+
+        fields.push(Field {
+            name:   "canonical_remote_urls",
+            value:  Some(config.param_canonical_remotes.to_string()),
+            origin: if args.canonical_remote_urls {
+                Origin::Flag("--canonical-remote-urls")
+            } else {
+                Origin::Default
+            },
+        });
+
+        fields.push(Field {
+            name:   "repo_dir",
+            value:  config.param_repo_dir.as_ref().map(helpers::path_to_string),
+            origin: if args.repo_dir.is_some() {
+                Origin::Flag("--repo-dir")
+            } else {
+                Origin::Unset
+            },
+        });
+
+        fields.push(Field {
+            name:   "source_prefix",
+            value:  config.param_src_prefix.as_ref().map(helpers::path_to_string),
+            origin: if args.source_prefix.is_some() {
+                Origin::Flag("--source-prefix")
+            } else {
+                Origin::Unset
+            },
+        });
+
+        Ok(Diagnosis {
+            rule,
+            service: Some(service),
+            fields,
+            warnings,
+        })
+    }
+}
+
+impl fmt::Display for Diagnosis {
+    /// Render the explanation as text; the repository token is masked, the credentials of the remote
+    /// URL are removed and only the first line of the commit message is shown.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Service: {}", self.service.map(|s| s.get_name()).unwrap_or("-"))?;
+        writeln!(f, "Detected by: {}", self.rule)?;
+
+        if !self.fields.is_empty() {
+            writeln!(f)?;
+        }
+
+        for field in &self.fields {
+            let value = match (field.name, &field.value) {
+                (_, None) => String::new(),
+                ("repo_token", Some(v)) => helpers::mask_secret(v),
+                ("git_remote_url", Some(v)) => sanitize_url(v, false).0,
+                (_, Some(v)) => v.lines().next().unwrap_or_default().to_string(),
+            };
+
+            writeln!(f, "{:<22} [{value}] ({})", field.name, field.origin)?;
+        }
+
+        writeln!(f)?;
+
+        if self.warnings.is_empty() {
+            writeln!(f, "No warning.")
+        } else {
+            writeln!(f, "Warnings:")?;
+
+            self.warnings.iter().try_for_each(|warning| writeln!(f, "- {warning}"))
+        }
+    }
+}
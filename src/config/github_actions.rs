@@ -1,18 +1,120 @@
 //! Loading of the GitHub Actions `GITHUB_*` environment variables.
+//!
+//! On top of the variables, the webhook event that triggered the workflow is read from the JSON
+//! file at `GITHUB_EVENT_PATH`. For the `pull_request` events, the checked out commit is a merge
+//! commit of the pull request into its base branch, so the commit ID and the branch are taken from
+//! the head of the pull request described by the event.
 
 use super::{Config, Env, Result};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
 
-/// Fill `config` with the GitHub Actions environment variables.
+/// A Git reference of the pull request event, its head or its base.
+#[derive(Deserialize, Serialize)]
+struct EventRef {
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha: Option<String>,
+}
+
+/// The pull request described by a `pull_request` or `pull_request_target` event.
+#[derive(Deserialize, Serialize)]
+struct PullRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    number: Option<u64>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    head: Option<EventRef>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    base: Option<EventRef>,
+}
+
+/// The part of the webhook event payload of interest, every other field being ignored.
+#[derive(Deserialize, Serialize)]
+struct Event {
+    pull_request: Option<PullRequest>,
+}
+
+/// Read the pull request of the event payload at `GITHUB_EVENT_PATH`.
+///
+/// An unreadable or invalid payload is not an error: the `GITHUB_*` variables are used alone.
+fn load_pull_request(env: &Env) -> Result<Option<PullRequest>> {
+    let Some(path) = env.get_var("GITHUB_EVENT_PATH")? else {
+        return Ok(None);
+    };
+
+    let content = match env.read_file(Path::new(&path)) {
+        Ok(v) => v,
+        Err(err) => {
+            warn!("Cannot read the GitHub Actions event payload `{path}`: {err}");
+
+            return Ok(None);
+        }
+    };
+
+    match serde_json::from_str::<Event>(&content) {
+        Ok(event) => {
+            debug!(
+                "Read the GitHub Actions event payload `{path}` ({})",
+                if event.pull_request.is_some() {
+                    "pull request"
+                } else {
+                    "no pull request"
+                }
+            );
+
+            Ok(event.pull_request)
+        }
+        Err(err) => {
+            warn!("Invalid GitHub Actions event payload `{path}`: {err}");
+
+            Ok(None)
+        }
+    }
+}
+
+/// Read the part of the event payload at `GITHUB_EVENT_PATH` used by [`load_config`], as JSON, to
+/// be embedded in a snapshot.
+pub(super) fn load_event(env: &Env) -> Result<Option<serde_json::Value>> {
+    let Some(pull_request) = load_pull_request(env)? else {
+        return Ok(None);
+    };
+
+    let event = Event {
+        pull_request: Some(pull_request),
+    };
+
+    Ok(Some(serde_json::to_value(event)?))
+}
+
+/// Fill `config` with the GitHub Actions environment variables and event payload.
 pub(super) fn load_config(config: &mut Config, env: &Env) -> Result<()> {
     if let Some(v) = env.get_github_actions_branch()? {
         config.git_branch.replace(v);
     }
 
+    if let Some(v) = env.get_var("GITHUB_SHA")? {
+        config.git_id.replace(v);
+    }
+
     if let Some(v) = env.get_var("GITHUB_JOB")? {
         config.service_job_id.replace(v);
     }
 
     if let Some(v) = env.get_var("GITHUB_RUN_ID")? {
+        if let (Some(server), Some(repository)) = (env.get_var("GITHUB_SERVER_URL")?, env.get_var("GITHUB_REPOSITORY")?)
+        {
+            let server = server.trim_end_matches('/');
+
+            config
+                .service_build_url
+                .replace(format!("{server}/{repository}/actions/runs/{v}"));
+        }
+
         config.service_build_number.replace(v);
     }
 
@@ -24,5 +126,51 @@ pub(super) fn load_config(config: &mut Config, env: &Env) -> Result<()> {
         config.service_job_number.replace(v);
     }
 
+    // The explicit `COVERALLS_SERVICE_ATTEMPT` and `COVERALLS_COMPARE_*` variables win.
+    if config.service_attempt.is_none()
+        && let Some(v) = env.get_var("GITHUB_RUN_ATTEMPT")?
+    {
+        config.service_attempt.replace(v);
+    }
+
+    if let Some(pull_request) = load_pull_request(env)? {
+        if let Some(v) = pull_request.number {
+            config.service_pull_request.replace(v.to_string());
+        }
+
+        if let Some(head) = pull_request.head {
+            if let Some(v) = head.sha {
+                config.git_id.replace(v);
+            }
+
+            if let Some(v) = head.name {
+                config.git_branch.replace(v);
+            }
+        }
+
+        if let Some(base) = pull_request.base {
+            if config.compare_ref.is_none() {
+                config.compare_ref = base.name;
+            }
+
+            if config.compare_sha.is_none() {
+                config.compare_sha = base.sha;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Fill `config` for the `github` service, authenticated with the `GITHUB_TOKEN` of the workflow.
+///
+/// The job ID is the workflow run ID, as Coveralls expects for this service name.
+pub(super) fn load_token_config(config: &mut Config, env: &Env) -> Result<()> {
+    load_config(config, env)?;
+
+    if let Some(v) = env.get_var("GITHUB_RUN_ID")? {
+        config.service_job_id.replace(v);
+    }
+
     Ok(())
 }
@@ -5,9 +5,11 @@
 //! Coveralls variable sets) into a partially built configuration.
 
 mod appveyor;
+mod builder;
 mod buildkite;
 mod circleci;
 mod coveralls_env;
+mod doctor;
 mod generic;
 mod github_actions;
 mod jenkins;
@@ -16,20 +18,35 @@ mod travis;
 
 use crate::{
     cli_args::{CliArgs, CliService, CliServiceArgs},
-    git::GitInfos,
+    coverage::MergeMode,
+    git::{GitBackend, GitInfos, MessageFormat, sanitize_url},
     service::Service,
     env::Env,
     helpers,
 };
 
+pub use builder::ConfigBuilder;
+pub(crate) use doctor::Diagnosis;
 use itertools::Itertools;
 use simple_error::SimpleError;
 use log::{debug, info, warn};
 use std::{
     io::{Result, Error, ErrorKind},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
+/// Generate the read accessors of optional string fields.
+macro_rules! getters {
+    ($($(#[$doc:meta])* $name:ident;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(&self) -> Option<&str> {
+                self.$name.as_deref()
+            }
+        )*
+    };
+}
+
 /// Resolved configuration of a Coveralls job.
 ///
 /// A `Config` aggregates everything needed to finalize a coverage report: the selected
@@ -38,8 +55,9 @@ use std::{
 ///
 /// It is built from the command line or from the environment (see
 /// [`Config::load_from_environment`]); service-specific environment variables are read first, then
-/// overridden by command line arguments, before the local parameters are applied. [`Config::show`]
-/// logs the whole resolved configuration.
+/// overridden by command line arguments, before the local parameters are applied. It can also be
+/// built programmatically with [`Config::builder`]. [`Config::show`] logs the whole resolved
+/// configuration.
 pub struct Config {
     pub(crate) service:               Service,
     pub(crate) flag_name:             Option<String>,
@@ -54,6 +72,9 @@ pub struct Config {
     pub(crate) service_job_name:      Option<String>,
     pub(crate) service_job_number:    Option<String>,
     pub(crate) service_repo_name:     Option<String>,
+    pub(crate) service_attempt:       Option<String>,
+    pub(crate) compare_ref:           Option<String>,
+    pub(crate) compare_sha:           Option<String>,
     pub(crate) git_id:                Option<String>,
     pub(crate) git_tag:               Option<String>,
     pub(crate) git_branch:            Option<String>,
@@ -67,14 +88,139 @@ pub struct Config {
     pub(crate) param_prune_absolutes: bool,
     pub(crate) param_prune_dirs:      Vec<PathBuf>,
     pub(crate) param_src_prefix:      Option<PathBuf>,
+    pub(crate) param_merge_mode:      MergeMode,
+    pub(crate) param_drop_branches:   bool,
+    pub(crate) param_repo_dir:        Option<PathBuf>,
+    pub(crate) param_message_format:  MessageFormat,
+    pub(crate) param_git_backend:     GitBackend,
+    pub(crate) param_keep_remotes:    Vec<String>,
+    pub(crate) param_canonical_remotes: bool,
+}
+
+/// A loader filling a configuration from the environment.
+type Loader = fn(&mut Config, &Env) -> Result<()>;
+
+/// The rule that selected the CI service of a configuration, and thus the variables that are loaded.
+pub(crate) enum Detection {
+    /// The service was named by a subcommand: its native variables are loaded.
+    Command(Service),
+
+    /// The service was named by `CI_NAME`: the generic `CI_*` variables are loaded.
+    CiName(Service),
+
+    /// The service was named by `COVERALLS_SERVICE_NAME`: the `COVERALLS_*` variables are loaded.
+    CoverallsServiceName(Service),
+
+    /// The service was guessed from its marker variable: its native variables are loaded.
+    Marker(Service),
+}
+
+impl Detection {
+    /// Detect the CI service from the environment.
+    ///
+    /// See [`Config::load_from_environment`] for the order of precedence of the rules.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if a recognized service name is not implemented, or if an
+    /// environment variable holds non-Unicode data.
+    pub(crate) fn from_env(env: &Env) -> Result<Option<Detection>> {
+        if let Some(name) = env.get_var("CI_NAME")? {
+            debug!("Found CI_NAME=`{name}`, loading generic CI configuration");
+
+            return if let Some(service) = Service::from_name(&name) {
+                Ok(Some(Detection::CiName(service)))
+            } else {
+                warn!("CI_NAME=`{name}` designates a service that is not implemented");
+
+                let msg = format!("Service name `{}` is not implemented", name);
+
+                Err(Error::new(ErrorKind::Other, SimpleError::new(msg)))
+            };
+        }
+
+        if let Some(name) = env.get_var("COVERALLS_SERVICE_NAME")? {
+            debug!("Found COVERALLS_SERVICE_NAME=`{name}`, loading Coveralls configuration");
+
+            return if let Some(service) = Service::from_name(&name) {
+                Ok(Some(Detection::CoverallsServiceName(service)))
+            } else {
+                warn!("COVERALLS_SERVICE_NAME=`{name}` designates a service that is not implemented");
+
+                let msg = format!("Service name `{}` is not implemented", name);
+
+                Err(Error::new(ErrorKind::Other, SimpleError::new(msg)))
+            };
+        }
+
+        if let Some(service) = Service::from_env(env)? {
+            debug!("Guessed service `{}` from its native variables", service.get_name());
+
+            return Ok(Some(Detection::Marker(service)));
+        }
+
+        debug!("No CI service detected from the environment");
+
+        Ok(None)
+    }
+
+    /// The detected service.
+    pub(crate) fn service(&self) -> Service {
+        match self {
+            Self::Command(service)
+            | Self::CiName(service)
+            | Self::CoverallsServiceName(service)
+            | Self::Marker(service) => *service,
+        }
+    }
+
+    /// The loaders applied, in order, to build the configuration.
+    fn loaders(&self) -> [Loader; 2] {
+        match self {
+            Self::Command(_) | Self::Marker(_) => [Config::load_common_variables, Config::load_service_variables],
+            Self::CiName(_) => [Config::load_common_variables, generic::load_config],
+            Self::CoverallsServiceName(_) => [Config::load_common_variables, coveralls_env::load_config],
+        }
+    }
+
+    /// Build the configuration of the detected service from the environment.
+    fn load(&self, env: &Env) -> Result<Config> {
+        let mut config = Config::blank(self.service());
+
+        for loader in self.loaders() {
+            loader(&mut config, env)?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Return the service named by a subcommand, with its arguments, if it designates one.
+fn command_service(cli: &CliService) -> Option<(Service, &CliServiceArgs)> {
+    match cli {
+        CliService::Actions(args) => Some((Service::GithubActions, args)),
+        CliService::Github(args) => Some((Service::Github, args)),
+        CliService::AppVeyor(args) => Some((Service::AppVeyor, args)),
+        CliService::BuildKite(args) => Some((Service::BuildKite, args)),
+        CliService::CircleCI(args) => Some((Service::CircleCI, args)),
+        CliService::Jenkins(args) => Some((Service::Jenkins, args)),
+        CliService::Semaphore(args) => Some((Service::Semaphore, args)),
+        CliService::Travis(args) => Some((Service::Travis, args)),
+        CliService::Env
+        | CliService::Compare(_)
+        | CliService::Validate
+        | CliService::Snapshot
+        | CliService::Doctor(_) => None,
+    }
 }
 
 impl Config {
-    fn new(service: Service, env: &Env) -> Result<Config> {
-        Ok(Config {
+    /// Build a configuration for `service` with no value set.
+    fn blank(service: Service) -> Config {
+        Config {
             service,
-            flag_name: env.get_var("COVERALLS_FLAG_NAME")?,
-            repo_token: env.get_var("COVERALLS_REPO_TOKEN")?,
+            flag_name: None,
+            repo_token: None,
             service_project_id: None,
             service_build_id: None,
             service_build_version: None,
@@ -85,23 +231,220 @@ impl Config {
             service_job_name: None,
             service_job_number: None,
             service_repo_name: None,
+            service_attempt: None,
+            compare_ref: None,
+            compare_sha: None,
             param_prune_absolutes: false,
             param_prune_dirs: vec![],
             param_src_prefix: None,
-            git_id: env.get_var("GIT_ID")?,
-            git_message: env.get_var("GIT_MESSAGE")?,
-            git_author_name: env.get_var("GIT_AUTHOR_NAME")?,
-            git_author_email: env.get_var("GIT_AUTHOR_EMAIL")?,
-            git_committer_name: env.get_var("GIT_COMMITTER_NAME")?,
-            git_committer_email: env.get_var("GIT_COMMITTER_EMAIL")?,
-            git_remote_name: env.get_var("GIT_REMOTE")?,
-            git_remote_url: env.get_var("GIT_URL")?,
-            git_tag: env.get_var("GIT_TAG")?,
-            git_branch: match env.get_var("GIT_BRANCH")? {
-                Some(v) => Some(v),
-                None => env.get_var("BRANCH_NAME")?,
-            },
-        })
+            param_merge_mode: MergeMode::default(),
+            param_drop_branches: false,
+            param_repo_dir: None,
+            param_message_format: MessageFormat::default(),
+            param_git_backend: GitBackend::default(),
+            param_keep_remotes: vec![],
+            param_canonical_remotes: false,
+            git_id: None,
+            git_message: None,
+            git_author_name: None,
+            git_author_email: None,
+            git_committer_name: None,
+            git_committer_email: None,
+            git_remote_name: None,
+            git_remote_url: None,
+            git_tag: None,
+            git_branch: None,
+        }
+    }
+
+    fn new(service: Service, env: &Env) -> Result<Config> {
+        let mut config = Config::blank(service);
+
+        config.load_common_variables(env)?;
+        Ok(config)
+    }
+
+    /// Load the variables common to every service (`COVERALLS_REPO_TOKEN`, `GIT_*`, ...).
+    fn load_common_variables(&mut self, env: &Env) -> Result<()> {
+        macro_rules! load {
+            ($($field:ident <- $name:literal;)*) => {
+                $(
+                    if let Some(v) = env.get_var($name)? {
+                        self.$field.replace(v);
+                    }
+                )*
+            };
+        }
+
+        load! {
+            flag_name <- "COVERALLS_FLAG_NAME";
+            repo_token <- "COVERALLS_REPO_TOKEN";
+            service_attempt <- "COVERALLS_SERVICE_ATTEMPT";
+            compare_ref <- "COVERALLS_COMPARE_REF";
+            compare_sha <- "COVERALLS_COMPARE_SHA";
+            git_id <- "GIT_ID";
+            git_message <- "GIT_MESSAGE";
+            git_author_name <- "GIT_AUTHOR_NAME";
+            git_author_email <- "GIT_AUTHOR_EMAIL";
+            git_committer_name <- "GIT_COMMITTER_NAME";
+            git_committer_email <- "GIT_COMMITTER_EMAIL";
+            git_remote_name <- "GIT_REMOTE";
+            git_remote_url <- "GIT_URL";
+            git_tag <- "GIT_TAG";
+        }
+
+        match env.get_var("GIT_BRANCH")? {
+            Some(v) => {
+                self.git_branch.replace(v);
+            }
+            None => {
+                if let Some(v) = env.get_var("BRANCH_NAME")? {
+                    self.git_branch.replace(v);
+                }
+            }
+        }
+
+        // The `github` service authenticates with the workflow token when no repository token is set.
+        if self.service == Service::Github
+            && self.repo_token.is_none()
+            && let Some(v) = env.get_var("GITHUB_TOKEN")?
+        {
+            self.repo_token.replace(v);
+        }
+
+        Ok(())
+    }
+
+    /// Start building a configuration for `service` from scratch, without reading the environment.
+    ///
+    /// ```rust
+    /// use coveralls::{Config, Service};
+    ///
+    /// let config = Config::builder(Service::CircleCI)
+    ///     .repo_token("my-token")
+    ///     .git_branch("main")
+    ///     .prune_absolutes(true)
+    ///     .prune_dir("target")
+    ///     .build();
+    ///
+    /// assert_eq!(config.repo_token(), Some("my-token"));
+    /// ```
+    pub fn builder(service: Service) -> ConfigBuilder {
+        ConfigBuilder::new(Config::blank(service))
+    }
+
+    /// The CI service of the job.
+    pub fn service(&self) -> Service {
+        self.service
+    }
+
+    getters! {
+        /// The Coveralls flag name.
+        flag_name;
+        /// The Coveralls repository token.
+        repo_token;
+        /// The service project ID.
+        service_project_id;
+        /// The service build ID.
+        service_build_id;
+        /// The service build version.
+        service_build_version;
+        /// The service build number.
+        service_build_number;
+        /// The service build URL.
+        service_build_url;
+        /// The service pull request.
+        service_pull_request;
+        /// The service job ID.
+        service_job_id;
+        /// The service job name.
+        service_job_name;
+        /// The service job number.
+        service_job_number;
+        /// The service repository name.
+        service_repo_name;
+        /// The attempt number of the job, when it is re-run.
+        service_attempt;
+        /// The Git reference the coverage is compared with.
+        compare_ref;
+        /// The Git commit SHA the coverage is compared with.
+        compare_sha;
+        /// The Git commit ID.
+        git_id;
+        /// The Git tag.
+        git_tag;
+        /// The Git branch.
+        git_branch;
+        /// The Git commit message.
+        git_message;
+        /// The Git author name.
+        git_author_name;
+        /// The Git author email.
+        git_author_email;
+        /// The Git committer name.
+        git_committer_name;
+        /// The Git committer email.
+        git_committer_email;
+        /// The Git remote name.
+        git_remote_name;
+        /// The Git remote URL.
+        git_remote_url;
+    }
+
+    /// Whether source files with an absolute path are pruned from the report.
+    pub fn prune_absolutes(&self) -> bool {
+        self.param_prune_absolutes
+    }
+
+    /// The directories pruned from the report.
+    pub fn prune_dirs(&self) -> &[PathBuf] {
+        &self.param_prune_dirs
+    }
+
+    /// The prefix prepended to every source file name.
+    pub fn source_prefix(&self) -> Option<&Path> {
+        self.param_src_prefix.as_deref()
+    }
+
+    /// How the hit counts of duplicate source files are merged.
+    pub fn merge_mode(&self) -> MergeMode {
+        self.param_merge_mode
+    }
+
+    /// Whether the branch data is dropped from the report.
+    pub fn drop_branches(&self) -> bool {
+        self.param_drop_branches
+    }
+
+    /// The directory the Git repository is searched from, the current directory when unset.
+    pub fn repo_dir(&self) -> Option<&Path> {
+        self.param_repo_dir.as_deref()
+    }
+
+    /// Which part of the commit message is collected from the repository.
+    pub fn message_format(&self) -> MessageFormat {
+        self.param_message_format
+    }
+
+    /// The backend reading the local Git repository.
+    pub fn git_backend(&self) -> GitBackend {
+        self.param_git_backend
+    }
+
+    /// The names of the Git remotes sent to Coveralls, every remote when empty.
+    pub fn keep_remotes(&self) -> &[String] {
+        &self.param_keep_remotes
+    }
+
+    /// Tell whether the SSH and scp-like remote URLs are turned into HTTPS URLs.
+    pub fn canonical_remotes(&self) -> bool {
+        self.param_canonical_remotes
+    }
+
+    /// Tell whether the Git metadata comes from the configuration only, without reading any
+    /// repository (the `none` Git backend).
+    pub fn is_offline(&self) -> bool {
+        self.param_git_backend == GitBackend::None
     }
 
     /// Load the environment variables native to `self.service` into the configuration.
@@ -114,6 +457,7 @@ impl Config {
             Service::BuildKite => buildkite::load_config(self, env),
             Service::CircleCI => circleci::load_config(self, env),
             Service::GithubActions => github_actions::load_config(self, env),
+            Service::Github => github_actions::load_token_config(self, env),
             Service::Jenkins => jenkins::load_config(self, env),
             Service::Semaphore => semaphore::load_config(self, env),
             Service::Travis => travis::load_config(self, env),
@@ -130,26 +474,16 @@ impl Config {
     ///
     /// Returns an [`std::io::Error`] if an environment variable holds non-Unicode data.
     pub(crate) fn load_from_command(cli: &CliArgs, env: &Env) -> Result<Option<Config>> {
-        let (service, args) = match &cli.service {
-            CliService::Actions(args) => (Service::GithubActions, args),
-            CliService::AppVeyor(args) => (Service::AppVeyor, args),
-            CliService::BuildKite(args) => (Service::BuildKite, args),
-            CliService::CircleCI(args) => (Service::CircleCI, args),
-            CliService::Jenkins(args) => (Service::Jenkins, args),
-            CliService::Semaphore(args) => (Service::Semaphore, args),
-            CliService::Travis(args) => (Service::Travis, args),
-            CliService::Env => {
-                debug!("No service subcommand provided, will guess the service from the environment");
-
-                return Ok(None);
-            }
+        let Some((service, args)) = command_service(&cli.service) else {
+            debug!("The subcommand does not designate a service, will guess it from the environment if needed");
+
+            return Ok(None);
         };
 
         debug!("Loading configuration from the `{}` subcommand", service.get_name());
 
-        let mut config = Config::new(service, env)?;
+        let mut config = Detection::Command(service).load(env)?;
 
-        config.load_service_variables(env)?;
         config.configure(args);
         Ok(Some(config))
     }
@@ -170,52 +504,44 @@ impl Config {
     /// Returns an [`std::io::Error`] if a recognized service name is not implemented, or if an
     /// environment variable holds non-Unicode data.
     pub fn load_from_environment(env: &Env) -> Result<Option<Config>> {
-        if let Some(name) = env.get_var("CI_NAME")? {
-            debug!("Found CI_NAME=`{name}`, loading generic CI configuration");
-
-            return if let Some(service) = Service::from_name(&name) {
-                let mut config = Config::new(service, env)?;
-                generic::load_config(&mut config, env)?;
-
-                Ok(Some(config))
-            } else {
-                warn!("CI_NAME=`{name}` designates a service that is not implemented");
-
-                let msg = format!("Service name `{}` is not implemented", name);
-
-                Err(Error::new(ErrorKind::Other, SimpleError::new(msg)))
-            };
-        }
-
-        if let Some(name) = env.get_var("COVERALLS_SERVICE_NAME")? {
-            debug!("Found COVERALLS_SERVICE_NAME=`{name}`, loading Coveralls configuration");
-
-            return if let Some(service) = Service::from_name(&name) {
-                let mut config = Config::new(service, env)?;
-                coveralls_env::load_config(&mut config, env)?;
-
-                Ok(Some(config))
-            } else {
-                warn!("COVERALLS_SERVICE_NAME=`{name}` designates a service that is not implemented");
-
-                let msg = format!("Service name `{}` is not implemented", name);
-
-                Err(Error::new(ErrorKind::Other, SimpleError::new(msg)))
-            };
+        match Detection::from_env(env)? {
+            Some(detection) => Ok(Some(detection.load(env)?)),
+            None => Ok(None),
         }
+    }
 
-        if let Some(service) = Service::from_env(env)? {
-            debug!("Guessed service `{}` from its native variables", service.get_name());
+    /// Read every variable that any configuration loader may read, discarding the results.
+    ///
+    /// This runs the service detection, the generic and Coveralls loaders and the native loader of
+    /// every service, so that a recording [`Env`] sees the whole set of relevant variables.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if an environment variable holds non-Unicode data.
+    pub(crate) fn probe_environment(env: &Env) -> Result<()> {
+        env.get_var("CI_NAME")?;
+        env.get_var("COVERALLS_SERVICE_NAME")?;
+        Service::from_env(env)?;
 
+        for service in Service::ALL {
             let mut config = Config::new(service, env)?;
-            config.load_service_variables(env)?;
 
-            return Ok(Some(config));
+            config.load_service_variables(env)?;
+            generic::load_config(&mut config, env)?;
+            coveralls_env::load_config(&mut config, env)?;
         }
 
-        debug!("No CI service detected from the environment");
+        Ok(())
+    }
 
-        Ok(None)
+    /// Read the part of the GitHub Actions event payload that the loaders use, if any, so that a
+    /// snapshot can embed it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if `GITHUB_EVENT_PATH` holds non-Unicode data.
+    pub(crate) fn probe_github_event(env: &Env) -> Result<Option<serde_json::Value>> {
+        github_actions::load_event(env)
     }
 
     fn configure(&mut self, args: &CliServiceArgs) {
@@ -267,6 +593,18 @@ impl Config {
             self.service_job_number.replace(v.clone());
         }
 
+        if let Some(v) = &args.service_attempt {
+            self.service_attempt.replace(v.clone());
+        }
+
+        if let Some(v) = &args.compare_ref {
+            self.compare_ref.replace(v.clone());
+        }
+
+        if let Some(v) = &args.compare_sha {
+            self.compare_sha.replace(v.clone());
+        }
+
         if let Some(v) = &args.git_id {
             self.git_id.replace(v.clone());
         }
@@ -311,7 +649,10 @@ impl Config {
     /// Apply the local processing parameters from the global command line arguments.
     ///
     /// These are the report-shaping options that are independent from the CI service: pruning of
-    /// absolute paths, list of pruned directories and the source prefix to prepend to every file.
+    /// absolute paths, list of pruned directories, the source prefix to prepend to every file, the
+    /// merge mode of duplicate files, the removal of branch data, the directory of the Git
+    /// repository, the collected part of the commit message, the Git backend and the handling of the
+    /// Git remotes.
     pub(crate) fn init_parameters(mut self, args: &CliArgs) -> Self {
         self.param_prune_absolutes = args.prune_absolutes;
 
@@ -323,6 +664,19 @@ impl Config {
             self.param_prune_dirs = dirs.clone();
         }
 
+        self.param_merge_mode = args.merge_mode;
+        self.param_drop_branches = args.no_branches;
+
+        if let Some(dir) = &args.repo_dir {
+            self.param_repo_dir.replace(dir.clone());
+        }
+
+        self.param_message_format = args.commit_message;
+
+        self.param_git_backend = if args.offline { GitBackend::None } else { args.git_backend };
+        self.param_keep_remotes = args.keep_remote.clone().unwrap_or_default();
+        self.param_canonical_remotes = args.canonical_remote_urls;
+
         debug!(
             "Parameters initialized (prune absolutes: {}, prune dirs: {}, source prefix: {})",
             self.param_prune_absolutes,
@@ -348,6 +702,12 @@ impl Config {
             .map(helpers::path_to_string)
             .unwrap_or_else(String::new);
 
+        let repo_dir = self
+            .param_repo_dir
+            .as_ref()
+            .map(helpers::path_to_string)
+            .unwrap_or_default();
+
         let git_id = self.git_id.as_ref().or_else(|| git.map(|v| &v.head.id));
         let git_tag = self.git_tag.as_ref();
         let git_branch = self.git_branch.as_ref().or_else(|| git.map(|v| &v.branch));
@@ -381,12 +741,20 @@ impl Config {
         let git_remote_url = self
             .git_remote_url
             .as_ref()
-            .or_else(|| git.and_then(|v| v.remotes.get(0).map(|v| &v.url)));
+            .or_else(|| git.and_then(|v| v.remotes.get(0).map(|v| &v.url)))
+            .map(|url| sanitize_url(url, self.param_canonical_remotes).0);
 
         info!("Parameters:");
         info!("Prune absolute paths:  {}", self.param_prune_absolutes);
         info!("Prune directories: ... [{prune_dirs}]");
         info!("Source prefix: ....... [{source_prefix}]");
+        info!("Merge mode: .......... {:?}", self.param_merge_mode);
+        info!("Drop branches: ....... {}", self.param_drop_branches);
+        info!("Repository directory:  [{repo_dir}]");
+        info!("Commit message: ...... {:?}", self.param_message_format);
+        info!("Git backend: ......... {}", self.param_git_backend.name());
+        info!("Kept remotes: ........ [{}]", self.param_keep_remotes.join(", "));
+        info!("Canonical remote URLs: {}", self.param_canonical_remotes);
         info!("");
 
         info!("Configuration:");
@@ -442,6 +810,13 @@ impl Config {
             self.service_job_number.as_ref().unwrap_or(&empty)
         );
 
+        info!(
+            "Service attempt: ..... [{}]",
+            self.service_attempt.as_ref().unwrap_or(&empty)
+        );
+
+        info!("Compare ref: ......... [{}]", self.compare_ref.as_ref().unwrap_or(&empty));
+        info!("Compare SHA: ......... [{}]", self.compare_sha.as_ref().unwrap_or(&empty));
         info!("Git ID: .............. [{}]", git_id.unwrap_or(&empty));
         info!("Git branch: .......... [{}]", git_branch.unwrap_or(&empty));
         info!("Git tag: ............. [{}]", git_tag.unwrap_or(&empty));
@@ -450,7 +825,7 @@ impl Config {
         info!("Git committer name: .. [{}]", git_committer_name.unwrap_or(&empty));
         info!("Git committer email: . [{}]", git_committer_email.unwrap_or(&empty));
         info!("Git remote name: ..... [{}]", git_remote_name.unwrap_or(&empty));
-        info!("Git remote URL: ...... [{}]", git_remote_url.unwrap_or(&empty));
+        info!("Git remote URL: ...... [{}]", git_remote_url.unwrap_or_default());
         info!("Git message: ......... [{}]", git_message.unwrap_or(&empty));
         info!("");
     }
@@ -1,41 +1,318 @@
-use super::git::GitInfos;
-use serde::{Deserialize, Serialize};
+use super::{export, git::GitInfos, validate::Validation};
+use serde::{
+    de::{DeserializeSeed, Error as _, MapAccess, SeqAccess, Visitor},
+    Deserialize,
+    Deserializer,
+    Serialize,
+};
+
+use simple_error::SimpleError;
 use log::{debug, trace};
-use std::io::{Cursor, Read, Result};
+use serde_json::{Map, Value};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{BufReader, Cursor, Error, ErrorKind, Read, Result, Write},
+    str::FromStr,
+};
+
+/// How the hit counts of two entries of the same source file are combined.
+///
+/// Parses from `max` or `sum`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum MergeMode {
+    /// Keep the highest count: a line is as covered as in the most covering entry.
+    #[default]
+    Max,
+
+    /// Add the counts up, as if every entry came from a distinct run.
+    Sum,
+}
+
+impl MergeMode {
+    /// Combine two hit counts.
+    fn combine(&self, a: usize, b: usize) -> usize {
+        match self {
+            Self::Max => a.max(b),
+            Self::Sum => a.saturating_add(b),
+        }
+    }
+}
+
+impl FromStr for MergeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "max" => Ok(Self::Max),
+            "sum" => Ok(Self::Sum),
+            _ => Err(format!("unknown merge mode `{s}`, expected `max` or `sum`")),
+        }
+    }
+}
+
+/// A branch of a source file, one `[line, block, branch, hits]` quadruple of the Coveralls format.
+///
+/// ```rust
+/// use coveralls::Branch;
+///
+/// let branch = Branch::new(12, 0, 1, 3);
+///
+/// assert_eq!((branch.line(), branch.block(), branch.branch(), branch.hits()), (12, 0, 1, 3));
+/// ```
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct Branch {
+    pub(crate) line:   usize,
+    pub(crate) block:  usize,
+    pub(crate) branch: usize,
+    pub(crate) hits:   usize,
+}
+
+impl Branch {
+    /// Create a branch from its line number (starting at 1), block and branch numbers, and hit count.
+    pub fn new(line: usize, block: usize, branch: usize, hits: usize) -> Self {
+        Self {
+            line,
+            block,
+            branch,
+            hits,
+        }
+    }
+
+    /// The line of the branch, starting at 1.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    /// The block number, identifying the condition on the line.
+    pub fn block(&self) -> usize {
+        self.block
+    }
+
+    /// The branch number within the block.
+    pub fn branch(&self) -> usize {
+        self.branch
+    }
+
+    /// How many times the branch was taken.
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    /// The `(line, block, branch)` triple identifying the branch in its file.
+    fn key(&self) -> (usize, usize, usize) {
+        (self.line, self.block, self.branch)
+    }
+}
+
+/// (De)serialization of the branches as the flat array of quadruples of the Coveralls format.
+mod quadruples {
+    use super::Branch;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(branches: &[Branch], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(
+            branches
+                .iter()
+                .flat_map(|branch| [branch.line, branch.block, branch.branch, branch.hits]),
+        )
+    }
+
+    /// Rejects a flat array whose length is not a multiple of 4; when a report is read, the entry is
+    /// then diagnosed by the validation checks, which name its source file.
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Branch>, D::Error> {
+        let values = Option::<Vec<usize>>::deserialize(deserializer)?.unwrap_or_default();
+
+        if values.len() % 4 != 0 {
+            return Err(D::Error::custom(format!(
+                "`branches` has {} values, not a multiple of 4 (line, block, branch, hits)",
+                values.len()
+            )));
+        }
+
+        Ok(values
+            .chunks_exact(4)
+            .map(|v| Branch::new(v[0], v[1], v[2], v[3]))
+            .collect())
+    }
+}
 
 /// A single source file entry of a coverage report.
 ///
 /// This mirrors the `source_files` objects of the Coveralls JSON format: the file `name`, the
 /// digest of its contents and, for every line, the hit count (`None` for lines that are not
-/// relevant to coverage). The optional `branches` and `source` fields are kept as they are when
-/// present in the input.
-#[derive(Debug, Serialize, Deserialize)]
-pub(crate) struct SourceFile {
+/// relevant to coverage). The optional `branches` (see [`Branch`]) and `source` fields are kept
+/// when present in the input.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceFile {
     pub(crate) name:          String,
     pub(crate) source_digest: String,
     pub(crate) coverage:      Vec<Option<usize>>,
 
-    #[serde(skip_serializing_if = "Vec::<usize>::is_empty")]
-    #[serde(default)]
-    pub(crate) branches: Vec<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default, with = "quadruples")]
+    pub(crate) branches: Vec<Branch>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub(crate) source: Option<String>,
 }
 
+impl SourceFile {
+    /// Create a source file entry from its name, the digest of its contents and its per-line hits.
+    pub fn new(name: impl Into<String>, source_digest: impl Into<String>, coverage: Vec<Option<usize>>) -> Self {
+        Self {
+            name: name.into(),
+            source_digest: source_digest.into(),
+            coverage,
+            branches: vec![],
+            source: None,
+        }
+    }
+
+    /// The file name, as reported to Coveralls.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Rename the file.
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+    }
+
+    /// The digest of the file contents.
+    pub fn source_digest(&self) -> &str {
+        &self.source_digest
+    }
+
+    /// Replace the digest of the file contents.
+    pub fn set_source_digest(&mut self, source_digest: impl Into<String>) {
+        self.source_digest = source_digest.into();
+    }
+
+    /// The hit count of every line, `None` for the lines that are not relevant to coverage.
+    pub fn coverage(&self) -> &[Option<usize>] {
+        &self.coverage
+    }
+
+    /// Replace the hit counts of the lines.
+    pub fn set_coverage(&mut self, coverage: Vec<Option<usize>>) {
+        self.coverage = coverage;
+    }
+
+    /// The branches of the file.
+    pub fn branches(&self) -> &[Branch] {
+        &self.branches
+    }
+
+    /// Replace the branches of the file.
+    pub fn set_branches(&mut self, branches: Vec<Branch>) {
+        self.branches = branches;
+    }
+
+    /// The file contents, when embedded in the report.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Embed (or remove) the file contents.
+    pub fn set_source(&mut self, source: Option<String>) {
+        self.source = source;
+    }
+
+    /// Merge the coverage of `other`, another entry of the same file, into this one.
+    ///
+    /// Hit counts of the lines and of the branches are combined according to `mode`; a line is
+    /// relevant when it is relevant in either entry, and the line array grows to the longest of the
+    /// two. The name, digest and contents of this entry are kept (the contents of `other` are used
+    /// when this one has none).
+    pub fn merge(&mut self, other: SourceFile, mode: MergeMode) {
+        if self.coverage.len() < other.coverage.len() {
+            self.coverage.resize(other.coverage.len(), None);
+        }
+
+        for (hits, other) in self.coverage.iter_mut().zip(other.coverage) {
+            *hits = match (*hits, other) {
+                (Some(a), Some(b)) => Some(mode.combine(a, b)),
+                (a, b) => a.or(b),
+            };
+        }
+
+        for branch in other.branches {
+            match self.branches.iter_mut().find(|known| known.key() == branch.key()) {
+                Some(known) => known.hits = mode.combine(known.hits, branch.hits),
+                None => self.branches.push(branch),
+            }
+        }
+
+        if self.source.is_none() {
+            self.source = other.source;
+        }
+    }
+
+    /// Number of lines relevant to coverage.
+    pub(crate) fn lines_valid(&self) -> usize {
+        self.coverage.iter().filter(|hits| hits.is_some()).count()
+    }
+
+    /// Number of relevant lines hit at least once.
+    pub(crate) fn lines_covered(&self) -> usize {
+        self.coverage
+            .iter()
+            .filter(|hits| matches!(hits, Some(n) if *n > 0))
+            .count()
+    }
+
+    /// Normalize the branches: those on a line outside of the file are dropped, the entries of a
+    /// same branch are combined according to `mode`, and the branches are sorted by line, block and
+    /// branch number. Returns the number of dropped branches.
+    pub(crate) fn normalize_branches(&mut self, mode: MergeMode) -> usize {
+        let lines = self.coverage.len();
+        let mut dropped = 0;
+        let mut branches = BTreeMap::new();
+
+        for branch in self.branches.drain(..) {
+            if branch.line == 0 || branch.line > lines {
+                dropped += 1;
+
+                continue;
+            }
+
+            branches
+                .entry(branch.key())
+                .and_modify(|known: &mut Branch| known.hits = mode.combine(known.hits, branch.hits))
+                .or_insert(branch);
+        }
+
+        self.branches = branches.into_values().collect();
+
+        dropped
+    }
+
+    /// Number of branches.
+    pub(crate) fn branches_valid(&self) -> usize {
+        self.branches.len()
+    }
+
+    /// Number of branches taken at least once.
+    pub(crate) fn branches_covered(&self) -> usize {
+        self.branches.iter().filter(|branch| branch.hits > 0).count()
+    }
+}
+
 /// A coverage report, deserialized from and serialized to the Coveralls JSON format.
 ///
 /// A `Coverage` is the in-memory representation of a Coveralls job: the repository token, the CI
 /// service identifiers, the optional Git metadata and the list of covered source files. It is
 /// parsed from a reader with [`Coverage::from_reader`], enriched in place by a
 /// [`CoverallsManager`](crate::CoverallsManager), and finally serialized back for upload with
-/// [`Coverage::new_reader`].
+/// [`Coverage::write_json`].
 ///
 /// Fields that are empty or absent are skipped during serialization so that the produced JSON
 /// stays close to what the Coveralls API expects.
-#[derive(Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize)]
 pub struct Coverage {
+    #[serde(skip_serializing_if = "String::is_empty")]
     #[serde(default)]
     pub(crate) repo_token: String,
 
@@ -50,10 +327,34 @@ pub struct Coverage {
     #[serde(default)]
     pub(crate) service_job_id: String,
 
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    pub(crate) service_job_number: String,
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    pub(crate) service_build_url: String,
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    pub(crate) service_attempt: String,
+
     #[serde(skip_serializing_if = "String::is_empty")]
     #[serde(default)]
     pub(crate) service_pull_request: String,
 
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    pub(crate) commit_sha: String,
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    pub(crate) compare_ref: String,
+
+    #[serde(skip_serializing_if = "String::is_empty")]
+    #[serde(default)]
+    pub(crate) compare_sha: String,
+
     #[serde(skip_serializing_if = "Option::is_none")]
     #[serde(default)]
     pub(crate) flag_name: Option<String>,
@@ -70,14 +371,238 @@ pub struct Coverage {
     pub(crate) source_files: Vec<SourceFile>,
 }
 
+/// Streaming deserialization of a report, the source files being passed to a filter one by one and
+/// the issues of those that cannot be parsed being recorded.
+struct ReportSeed<'v, F> {
+    filter:  F,
+    invalid: &'v mut Validation,
+}
+
+impl<'de, F: FnMut(SourceFile) -> Option<SourceFile>> DeserializeSeed<'de> for ReportSeed<'_, F> {
+    type Value = Coverage;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<Coverage, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, F: FnMut(SourceFile) -> Option<SourceFile>> Visitor<'de> for ReportSeed<'_, F> {
+    type Value = Coverage;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a Coveralls job object")
+    }
+
+    /// Read the `source_files` array entry by entry, the other (small) fields being collected and
+    /// deserialized as usual once the whole object is read.
+    fn visit_map<A: MapAccess<'de>>(mut self, mut map: A) -> std::result::Result<Coverage, A::Error> {
+        let mut fields = Map::new();
+        let mut sources = vec![];
+
+        while let Some(key) = map.next_key::<String>()? {
+            if key == "source_files" {
+                sources = map.next_value_seed(SourcesSeed {
+                    filter:  &mut self.filter,
+                    invalid: self.invalid,
+                })?;
+            } else {
+                fields.insert(key, map.next_value()?);
+            }
+        }
+
+        let mut coverage = Coverage::deserialize(Value::Object(fields)).map_err(A::Error::custom)?;
+
+        coverage.source_files = sources;
+
+        Ok(coverage)
+    }
+}
+
+/// Streaming deserialization of the `source_files` array, keeping the entries accepted by a filter.
+struct SourcesSeed<'a, F> {
+    filter:  &'a mut F,
+    invalid: &'a mut Validation,
+}
+
+impl<'de, F: FnMut(SourceFile) -> Option<SourceFile>> DeserializeSeed<'de> for SourcesSeed<'_, F> {
+    type Value = Vec<SourceFile>;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> std::result::Result<Vec<SourceFile>, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: FnMut(SourceFile) -> Option<SourceFile>> Visitor<'de> for SourcesSeed<'_, F> {
+    type Value = Vec<SourceFile>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("an array of source files")
+    }
+
+    /// Read every entry as JSON first, so that an entry which cannot be parsed is diagnosed by the
+    /// validation checks, with its index and name, rather than by a bare parsing error.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<Vec<SourceFile>, A::Error> {
+        let mut sources = vec![];
+        let mut files = 0;
+
+        while let Some(value) = seq.next_element::<Value>()? {
+            match SourceFile::deserialize(&value) {
+                Ok(source) => sources.extend((self.filter)(source)),
+                Err(err) => self.invalid.add_unparsed_file(files, &value, &err),
+            }
+
+            files += 1;
+        }
+
+        self.invalid.set_files(files);
+
+        Ok(sources)
+    }
+}
+
+/// Generate the accessors and the mutators of string fields.
+macro_rules! string_fields {
+    ($($(#[$doc:meta])* $name:ident, $setter:ident;)*) => {
+        $(
+            $(#[$doc])*
+            pub fn $name(&self) -> &str {
+                &self.$name
+            }
+
+            #[doc = concat!("Set the value returned by [`Coverage::", stringify!($name), "`].")]
+            pub fn $setter(&mut self, value: impl Into<String>) {
+                self.$name = value.into();
+            }
+        )*
+    };
+}
+
 impl Coverage {
+    string_fields! {
+        /// The Coveralls repository token.
+        repo_token, set_repo_token;
+        /// The CI service name.
+        service_name, set_service_name;
+        /// The service build number.
+        service_number, set_service_number;
+        /// The service job ID.
+        service_job_id, set_service_job_id;
+        /// The service job number.
+        service_job_number, set_service_job_number;
+        /// The URL of the build on the CI service.
+        service_build_url, set_service_build_url;
+        /// The attempt number of the job, when it is re-run.
+        service_attempt, set_service_attempt;
+        /// The service pull request.
+        service_pull_request, set_service_pull_request;
+        /// The SHA of the covered commit.
+        commit_sha, set_commit_sha;
+        /// The Git reference the coverage is compared with.
+        compare_ref, set_compare_ref;
+        /// The Git commit SHA the coverage is compared with.
+        compare_sha, set_compare_sha;
+        /// The date and time of the run.
+        run_at, set_run_at;
+    }
+
+    /// Create an empty report, to be filled with source files.
+    ///
+    /// ```rust
+    /// use coveralls::{Coverage, SourceFile};
+    ///
+    /// let mut coverage = Coverage::new();
+    ///
+    /// coverage.add_source_file(SourceFile::new(
+    ///     "src/lib.rs",
+    ///     "d41d8cd9",
+    ///     vec![Some(1), None, Some(0)],
+    /// ));
+    /// assert_eq!(coverage.source_files().len(), 1);
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The Coveralls flag name.
+    pub fn flag_name(&self) -> Option<&str> {
+        self.flag_name.as_deref()
+    }
+
+    /// Set (or remove) the Coveralls flag name.
+    pub fn set_flag_name(&mut self, flag_name: Option<String>) {
+        self.flag_name = flag_name;
+    }
+
+    /// The source files of the report.
+    pub fn source_files(&self) -> &[SourceFile] {
+        &self.source_files
+    }
+
+    /// The source files of the report, to be modified in place.
+    pub fn source_files_mut(&mut self) -> &mut Vec<SourceFile> {
+        &mut self.source_files
+    }
+
+    /// Append a source file to the report.
+    pub fn add_source_file(&mut self, source: SourceFile) {
+        self.source_files.push(source);
+    }
+
     /// Parse a coverage report in the Coveralls JSON format from a reader.
     ///
     /// # Errors
     ///
     /// Returns an [`std::io::Error`] if reading fails or if the data is not valid Coveralls JSON.
     pub fn from_reader<R: Read>(rdr: R) -> Result<Self> {
-        let coverage: Coverage = serde_json::from_reader(rdr)?;
+        Self::from_reader_filtered(rdr, Some)
+    }
+
+    /// Parse a coverage report in the Coveralls JSON format from a reader, passing every source file
+    /// to `filter` as soon as it is read: the returned entry is kept, `None` drops it.
+    ///
+    /// The input is streamed, so the dropped entries are never materialized beyond the one being read;
+    /// the kept entries are collected in the returned report.
+    ///
+    /// ```rust
+    /// use coveralls::{Coverage, SourceFile};
+    ///
+    /// let json = r#"{"source_files":[
+    ///     {"name":"src/lib.rs","source_digest":"1","coverage":[1]},
+    ///     {"name":"/usr/lib/rust/core.rs","source_digest":"2","coverage":[0]}
+    /// ]}"#;
+    ///
+    /// let keep_relative = |source: SourceFile| (!source.name().starts_with('/')).then_some(source);
+    /// let coverage = Coverage::from_reader_filtered(json.as_bytes(), keep_relative).unwrap();
+    ///
+    /// assert_eq!(coverage.source_files().len(), 1);
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if reading fails or if the data is not valid Coveralls JSON; the
+    /// source files that cannot be parsed are diagnosed as by [`Validation`], with their index and
+    /// name.
+    pub fn from_reader_filtered<R, F>(rdr: R, filter: F) -> Result<Self>
+    where
+        R: Read,
+        F: FnMut(SourceFile) -> Option<SourceFile>,
+    {
+        let mut deserializer = serde_json::Deserializer::from_reader(BufReader::new(rdr));
+        let mut invalid = Validation::default();
+        let coverage = ReportSeed {
+            filter,
+            invalid: &mut invalid,
+        }
+        .deserialize(&mut deserializer)?;
+
+        deserializer.end()?;
+
+        if !invalid.is_valid() {
+            let msg = invalid.to_string();
+
+            return Err(Error::new(ErrorKind::InvalidData, SimpleError::new(msg.trim_end())));
+        }
+
         debug!(
             "Parsed coverage report with {} source file(s)",
             coverage.source_files.len()
@@ -86,12 +611,25 @@ impl Coverage {
         Ok(coverage)
     }
 
-    /// Serialize the report to JSON and return a reader over the produced bytes.
+    /// Serialize the report to `writer` as JSON, without buffering the whole payload.
     ///
     /// This is what gets uploaded to the Coveralls API as the `json_file` part of the request.
     ///
     /// # Errors
     ///
+    /// Returns an [`std::io::Error`] if the report cannot be serialized or if writing fails.
+    pub fn write_json<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer(&mut writer, self)?;
+
+        writer.flush()
+    }
+
+    /// Serialize the report to JSON and return a reader over the produced bytes.
+    ///
+    /// The whole payload is held in memory; prefer [`Coverage::write_json`] for large reports.
+    ///
+    /// # Errors
+    ///
     /// Returns an [`std::io::Error`] if the report cannot be serialized to JSON.
     pub fn new_reader(&self) -> Result<Box<dyn Read>> {
         let json = serde_json::to_string(&self)?;
@@ -100,6 +638,50 @@ impl Coverage {
         Ok(Box::new(Cursor::new(json)))
     }
 
+    /// Write the report to `writer` in the LCOV tracefile format.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if writing fails.
+    pub fn write_lcov<W: Write>(&self, writer: W) -> Result<()> {
+        export::lcov::write(self, writer)
+    }
+
+    /// Write the report to `writer` as a Cobertura XML document.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if writing fails.
+    pub fn write_cobertura<W: Write>(&self, writer: W) -> Result<()> {
+        export::cobertura::write(self, writer)
+    }
+
+    /// Write a Markdown summary of the report to `writer`.
+    ///
+    /// The summary holds the total coverage, the files with the most uncovered lines and, when a
+    /// `baseline` report is given, the coverage deltas and the list of files changed since then.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if writing fails.
+    pub fn write_markdown_summary<W: Write>(&self, baseline: Option<&Coverage>, writer: W) -> Result<()> {
+        export::markdown::write(self, baseline, writer)
+    }
+
+    /// Line totals of the whole report, as `(valid, covered)`.
+    pub(crate) fn line_totals(&self) -> (usize, usize) {
+        self.source_files.iter().fold((0, 0), |(valid, covered), source| {
+            (valid + source.lines_valid(), covered + source.lines_covered())
+        })
+    }
+
+    /// Branch totals of the whole report, as `(valid, covered)`.
+    pub(crate) fn branch_totals(&self) -> (usize, usize) {
+        self.source_files.iter().fold((0, 0), |(valid, covered), source| {
+            (valid + source.branches_valid(), covered + source.branches_covered())
+        })
+    }
+
     /// Return the Git metadata attached to the report, if any.
     pub fn git(&self) -> Option<&GitInfos> {
         self.git.as_ref()
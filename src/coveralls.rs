@@ -1,11 +1,134 @@
-use crate::{config::Config, coverage::Coverage, git::GitInfos, api, helpers};
+use crate::{
+    config::Config,
+    coverage::{Coverage, MergeMode, SourceFile},
+    git::GitInfos,
+    service::Service,
+    spool::{SourceSpool, SpooledCoverage},
+    api,
+    helpers,
+};
 use simple_error::SimpleError;
-use log::{debug, info, warn};
+use log::{info, warn};
 use std::{
-    io::{Result, Error, ErrorKind},
-    path::PathBuf,
+    collections::HashMap,
+    io::{Read, Result, Error, ErrorKind},
+    path::Path,
 };
 
+/// Normalize the path of `source`, then drop it if pruned by the configuration or prefix it.
+fn prune_source(config: &Config, mut source: SourceFile) -> Option<SourceFile> {
+    let mut path = helpers::normalize_path(Path::new(&source.name));
+
+    if config.param_prune_absolutes && path.is_absolute() {
+        return None;
+    }
+
+    if config.param_prune_dirs.iter().any(|prefix| path.starts_with(prefix)) {
+        return None;
+    }
+
+    if let Some(prefix) = config.param_src_prefix.as_ref() {
+        path = prefix.join(path);
+    }
+
+    source.name = helpers::path_to_string(&path);
+
+    Some(source)
+}
+
+/// Log the outcome of the pruning.
+fn log_pruning(kept: usize, total: usize) {
+    info!(
+        stage = "prune",
+        kept,
+        pruned = total - kept;
+        "Kept {kept} of {total} source file(s) after pruning"
+    );
+}
+
+/// Merge `source` into `target`, another entry of the same file, logging a warning when their line
+/// counts or digests disagree.
+fn merge_duplicate(target: &mut SourceFile, source: SourceFile, mode: MergeMode) {
+    if target.coverage.len() != source.coverage.len() {
+        warn!(
+            "Duplicate entries of `{}` disagree on the line count ({} vs {})",
+            source.name,
+            target.coverage.len(),
+            source.coverage.len()
+        );
+    }
+
+    if target.source_digest != source.source_digest {
+        warn!("Duplicate entries of `{}` have different digests, the first one is kept", source.name);
+    }
+
+    target.merge(source, mode);
+}
+
+/// Log the outcome of the merge of the `merged` duplicate entries.
+fn log_merge(merged: usize) {
+    if merged > 0 {
+        info!(
+            stage = "merge",
+            merged;
+            "Merged {merged} duplicate source file entries"
+        );
+    }
+}
+
+/// Merge the source files sharing the same name, each merged entry keeping the position of the first
+/// one.
+fn merge_duplicates(sources: Vec<SourceFile>, mode: MergeMode) -> Vec<SourceFile> {
+    let total = sources.len();
+    let mut merged: Vec<SourceFile> = Vec::with_capacity(total);
+    let mut positions = HashMap::new();
+
+    for source in sources {
+        match positions.get(&source.name) {
+            Some(&idx) => merge_duplicate(&mut merged[idx], source, mode),
+            None => {
+                positions.insert(source.name.clone(), merged.len());
+                merged.push(source);
+            }
+        }
+    }
+
+    log_merge(total - merged.len());
+
+    merged
+}
+
+/// Drop the branches of `source` when configured, or else normalize them; returns the number of
+/// dropped branches.
+fn normalize_source(config: &Config, source: &mut SourceFile) -> usize {
+    if config.param_drop_branches {
+        let dropped = source.branches.len();
+
+        source.branches.clear();
+
+        return dropped;
+    }
+
+    let invalid = source.normalize_branches(config.param_merge_mode);
+
+    if invalid > 0 {
+        warn!("Dropped {invalid} branch(es) of `{}` on lines outside of the file", source.name);
+    }
+
+    invalid
+}
+
+/// Log the branch totals, once the branches are normalized.
+fn log_branches(valid: usize, covered: usize, dropped: usize) {
+    info!(
+        stage = "branches",
+        valid,
+        covered,
+        dropped;
+        "Kept {valid} branch(es), {covered} covered, dropped {dropped}"
+    );
+}
+
 /// Bridge between a [`Config`] and a [`Coverage`] report, in charge of finalizing and uploading it.
 ///
 /// The manager has no state of its own; it is a small handle whose two operations are applying a
@@ -19,34 +142,155 @@ impl CoverallsManager {
         CoverallsManager
     }
 
+    /// Read a coverage report from `rdr` into a [`SpooledCoverage`], pruning its source files
+    /// according to `config` as they are read.
+    ///
+    /// The report is streamed, so the pruned entries are never materialized and the kept ones are
+    /// written to the spool one at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if reading fails, if the data is not valid Coveralls JSON, or
+    /// if the spool cannot be written.
+    pub(crate) fn read_spooled<R: Read>(&self, config: &Config, rdr: R) -> Result<SpooledCoverage> {
+        let mut sources = SourceSpool::new()?;
+        let mut total = 0;
+        let mut spool_err = None;
+        let coverage = Coverage::from_reader_filtered(rdr, |source| {
+            total += 1;
+
+            if let Some(source) = prune_source(config, source)
+                && spool_err.is_none()
+                && let Err(err) = sources.push(&source)
+            {
+                spool_err.replace(err);
+            }
+
+            None
+        })?;
+
+        if let Some(err) = spool_err {
+            return Err(err);
+        }
+
+        log_pruning(sources.entries(), total);
+
+        Ok(SpooledCoverage {
+            coverage,
+            sources,
+        })
+    }
+
     /// Enrich a coverage report in place with the values held by the configuration.
     ///
-    /// This sets the service name, repository token, flag name and service identifiers on the
-    /// report, then resolves the Git metadata and prunes the source files according to the
-    /// configured rules:
+    /// This sets the service name, repository token, flag name, service identifiers (build URL, job
+    /// number, attempt...) and comparison references on the report, then resolves the Git metadata
+    /// (the `HEAD` commit ID becoming the `commit_sha` of the job) and prunes the source files
+    /// according to the configured rules:
     ///
     /// - Git information is fetched from the local repository when `fetch_git_infos` is `true` or when the report
     ///   carries none; otherwise the existing data is updated from the config.
-    /// - source files whose path is absolute are dropped when [`param_prune_absolutes`](Config) is set, those under a
-    ///   configured pruned directory are dropped, and a source prefix is prepended to the remaining paths when
-    ///   configured.
+    /// - source file paths are normalized (`./` and `..` are resolved lexically), then source files whose path is
+    ///   absolute are dropped when [`param_prune_absolutes`](Config) is set, those under a configured pruned directory
+    ///   are dropped, and a source prefix is prepended to the remaining paths when configured.
+    /// - source files ending up with the same name are merged into one entry (see [`SourceFile::merge`]), according to
+    ///   the configured [`MergeMode`].
+    /// - branches are dropped altogether when [`param_drop_branches`](Config) is set; otherwise those on a line outside
+    ///   of their file are dropped, the duplicated ones combined and the remaining ones sorted.
     ///
     /// # Errors
     ///
     /// Returns an [`std::io::Error`] if the Git metadata cannot be collected or is incomplete, or
     /// if the repository token is missing from both the report and the configuration.
-    pub fn apply_config(&self, config: &Config, coverage: &mut Coverage, mut fetch_git_infos: bool) -> Result<()> {
+    pub fn apply_config(&self, config: &Config, coverage: &mut Coverage, fetch_git_infos: bool) -> Result<()> {
+        self.apply_job(config, coverage, fetch_git_infos)?;
+
+        let total = coverage.source_files.len();
+        let sources = coverage
+            .source_files
+            .drain(..)
+            .filter_map(|source| prune_source(config, source))
+            .collect::<Vec<_>>();
+
+        log_pruning(sources.len(), total);
+        coverage.source_files = merge_duplicates(sources, config.param_merge_mode);
+
+        let mut dropped = 0;
+
+        for source in &mut coverage.source_files {
+            dropped += normalize_source(config, source);
+        }
+
+        let (valid, covered) = coverage.branch_totals();
+
+        log_branches(valid, covered, dropped);
+
+        Ok(())
+    }
+
+    /// Enrich a report read with [`read_spooled`](CoverallsManager::read_spooled) as
+    /// [`apply_config`](CoverallsManager::apply_config) does, its source files being already pruned:
+    /// the entries of every file are merged and their branches normalized, one file at a time, into a
+    /// new spool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] in the same cases as [`apply_config`](CoverallsManager::apply_config), or
+    /// if the spools cannot be read or written.
+    pub(crate) fn apply_config_spooled(
+        &self,
+        config: &Config,
+        report: &mut SpooledCoverage,
+        fetch_git_infos: bool,
+    ) -> Result<()> {
+        self.apply_job(config, &mut report.coverage, fetch_git_infos)?;
+
+        let mut processed = SourceSpool::new()?;
+        let (mut valid, mut covered, mut dropped) = (0, 0, 0);
+
+        for idx in 0..report.sources.files() {
+            let mut entries = report.sources.read_file(idx)?.into_iter();
+            let Some(mut source) = entries.next() else {
+                continue;
+            };
+
+            for other in entries {
+                merge_duplicate(&mut source, other, config.param_merge_mode);
+            }
+
+            dropped += normalize_source(config, &mut source);
+            valid += source.branches_valid();
+            covered += source.branches_covered();
+            processed.push(&source)?;
+        }
+
+        log_merge(report.sources.entries() - processed.entries());
+        log_branches(valid, covered, dropped);
+        report.sources = processed;
+
+        Ok(())
+    }
+
+    /// Set the fields of the job from the configuration and resolve its Git metadata, leaving its
+    /// source files untouched.
+    fn apply_job(&self, config: &Config, coverage: &mut Coverage, mut fetch_git_infos: bool) -> Result<()> {
         coverage.service_name = config.service.get_name().to_string();
 
+        let offline = config.is_offline();
+
         if let Some(infos) = coverage.git.as_mut() {
             infos.update(config)?;
+        } else if offline {
+            info!("Offline mode, the Git metadata comes from the configuration only");
+
+            coverage.git = GitInfos::from_config(config)?;
         } else {
             warn!("No Git information in the coverage report; it will be fetched from the local repository");
 
             fetch_git_infos = true;
         }
 
-        if fetch_git_infos {
+        if fetch_git_infos && !offline {
             info!("Fetching git infos...");
             let mut infos = GitInfos::default();
 
@@ -59,7 +303,11 @@ impl CoverallsManager {
         }
 
         if coverage.repo_token.is_empty() {
-            let msg = String::from("Repo token is missing, set the COVERALLS_REPO_TOKEN env var.");
+            let msg = if config.service == Service::Github {
+                "Repo token is missing, set the GITHUB_TOKEN (or COVERALLS_REPO_TOKEN) env var."
+            } else {
+                "Repo token is missing, set the COVERALLS_REPO_TOKEN env var."
+            };
 
             return Err(Error::new(ErrorKind::Other, SimpleError::new(msg)));
         }
@@ -80,33 +328,29 @@ impl CoverallsManager {
             coverage.service_job_id = v.clone();
         }
 
-        let mut sources = vec![];
-        let total = coverage.source_files.len();
-
-        'sources: for mut source in coverage.source_files.drain(..) {
-            let mut path = PathBuf::from(&source.name);
-
-            if config.param_prune_absolutes && path.is_absolute() {
-                continue;
-            }
+        if let Some(v) = config.service_job_number.as_ref() {
+            coverage.service_job_number = v.clone();
+        }
 
-            for prefix in &config.param_prune_dirs {
-                if path.starts_with(prefix) {
-                    continue 'sources;
-                }
-            }
+        if let Some(v) = config.service_build_url.as_ref() {
+            coverage.service_build_url = v.clone();
+        }
 
-            if let Some(prefix) = config.param_src_prefix.as_ref() {
-                path = prefix.join(path);
-            }
+        if let Some(v) = config.service_attempt.as_ref() {
+            coverage.service_attempt = v.clone();
+        }
 
-            source.name = helpers::path_to_string(&path);
-            sources.push(source)
+        if let Some(v) = config.compare_ref.as_ref() {
+            coverage.compare_ref = v.clone();
         }
 
-        debug!("Kept {} of {} source file(s) after pruning", sources.len(), total);
+        if let Some(v) = config.compare_sha.as_ref() {
+            coverage.compare_sha = v.clone();
+        }
 
-        coverage.source_files = sources;
+        if let Some(git) = coverage.git.as_ref() {
+            coverage.commit_sha = git.head.id.clone();
+        }
 
         Ok(())
     }
@@ -118,6 +362,17 @@ impl CoverallsManager {
     /// Returns an [`std::io::Error`] if the request cannot be built or sent, or if the API responds
     /// with a non-`200` status.
     pub fn send(&self, coverage: &Coverage) -> Result<()> {
-        api::send_to_api(coverage)
+        api::send_to_api(|writer| coverage.write_json(writer))
+    }
+
+    /// Upload a report read with [`read_spooled`](CoverallsManager::read_spooled), its source files
+    /// being read back one at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] in the same cases as [`send`](CoverallsManager::send), or if the
+    /// spool cannot be read back.
+    pub(crate) fn send_spooled(&self, report: &mut SpooledCoverage) -> Result<()> {
+        api::send_to_api(|writer| report.write_json(writer))
     }
 }
@@ -0,0 +1,152 @@
+//! Parser of dotenv (`.env`) files, used as an [`EnvSource`].
+
+use super::EnvSource;
+use simple_error::SimpleError;
+use log::debug;
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::read_to_string,
+    io::{Result, Error, ErrorKind},
+    path::Path,
+};
+
+/// Build the error returned for an invalid line of a dotenv file.
+fn syntax_error(line: usize, msg: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, SimpleError::new(format!("Line {line}: {msg}")))
+}
+
+/// Parse the value part of a `KEY=value` line.
+///
+/// Single-quoted values are taken literally, double-quoted values support the `\n`, `\t`, `\"` and
+/// `\\` escapes, and unquoted values end at the first ` #` (a trailing comment) and are trimmed.
+fn parse_value(raw: &str, line: usize) -> Result<String> {
+    let raw = raw.trim();
+
+    if let Some(rest) = raw.strip_prefix('\'') {
+        return match rest.find('\'') {
+            Some(end) => Ok(rest[..end].to_string()),
+            None => Err(syntax_error(line, "unterminated single-quoted value")),
+        };
+    }
+
+    if let Some(rest) = raw.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => return Ok(value),
+                '\\' => match chars.next() {
+                    Some('n') => value.push('\n'),
+                    Some('t') => value.push('\t'),
+                    Some(c) => value.push(c),
+                    None => break,
+                },
+                c => value.push(c),
+            }
+        }
+
+        return Err(syntax_error(line, "unterminated double-quoted value"));
+    }
+
+    let value = match raw.find(" #") {
+        Some(idx) => &raw[..idx],
+        None => raw,
+    };
+
+    Ok(value.trim_end().to_string())
+}
+
+/// Variables read from a dotenv file.
+///
+/// The accepted syntax is the common subset of the dotenv implementations: one `KEY=value` per
+/// line, optionally prefixed with `export`, blank lines and `#` comments, and single- or
+/// double-quoted values. This is typically used to replay the environment of a CI job locally.
+///
+/// ```rust
+/// use coveralls::{DotEnv, EnvSource};
+///
+/// let vars = DotEnv::parse("# Circle-CI\nexport CIRCLECI=true\nCIRCLE_BRANCH='main'\n").unwrap();
+///
+/// assert_eq!(vars.var_os("CIRCLE_BRANCH").unwrap(), "main");
+/// ```
+#[derive(Debug, Default)]
+pub struct DotEnv {
+    vars: HashMap<String, String>,
+}
+
+impl DotEnv {
+    /// Parse the contents of a dotenv file.
+    ///
+    /// When a variable is defined several times, the last definition wins.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] naming the offending line if a line is neither blank, a comment
+    /// nor a valid `KEY=value` definition.
+    pub fn parse(content: &str) -> Result<Self> {
+        let mut vars = HashMap::new();
+
+        for (idx, line) in content.lines().enumerate() {
+            let line_number = idx + 1;
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let line = line.strip_prefix("export ").unwrap_or(line);
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(syntax_error(line_number, "expected `KEY=value`"));
+            };
+
+            let key = key.trim();
+
+            if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+                return Err(syntax_error(line_number, "invalid variable name"));
+            }
+
+            vars.insert(key.to_string(), parse_value(value, line_number)?);
+        }
+
+        Ok(Self {
+            vars,
+        })
+    }
+
+    /// Read and parse a dotenv file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the file cannot be read or is not a valid dotenv file.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let env = Self::parse(&read_to_string(path)?).map_err(|err| {
+            Error::new(
+                err.kind(),
+                SimpleError::new(format!("Invalid dotenv file `{}`: {err}", path.display())),
+            )
+        })?;
+
+        debug!("Read {} variable(s) from `{}`", env.vars.len(), path.display());
+
+        Ok(env)
+    }
+
+    /// Number of variables defined in the file.
+    pub fn len(&self) -> usize {
+        self.vars.len()
+    }
+
+    /// Tell whether the file defines no variable.
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+}
+
+impl EnvSource for DotEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.vars.get(name).map(OsString::from)
+    }
+}
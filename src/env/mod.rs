@@ -0,0 +1,206 @@
+//! Access to the environment variables, and the sources they can be read from.
+//!
+//! [`Env`] is the accessor used by the configuration loaders; it reads its variables through an
+//! [`EnvSource`]: the process environment, an in-memory map, a dotenv file ([`DotEnv`]), a recorded
+//! CI environment ([`EnvSnapshot`]) or a stack of those ([`LayeredEnv`]).
+
+mod dotenv;
+mod snapshot;
+mod source;
+
+pub use self::{
+    dotenv::DotEnv,
+    snapshot::EnvSnapshot,
+    source::{EnvSource, LayeredEnv, ProcessEnv},
+};
+
+use regex::Regex;
+use simple_error::SimpleError;
+use log::trace;
+use std::{
+    cell::RefCell,
+    ffi::OsString,
+    io::{Result, Error, ErrorKind},
+    path::Path,
+    rc::Rc,
+};
+
+/// Names of the variables looked up through an [`Env`] returned by [`Env::record`], in lookup order.
+#[derive(Clone, Default)]
+pub(crate) struct ReadLog(Rc<RefCell<Vec<String>>>);
+
+impl ReadLog {
+    /// Number of lookups so far, to be passed later to [`ReadLog::names_since`].
+    pub(crate) fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    /// The distinct names looked up since the `start`-th lookup, in order of first lookup.
+    pub(crate) fn names_since(&self, start: usize) -> Vec<String> {
+        let mut names: Vec<String> = vec![];
+
+        for name in &self.0.borrow()[start..] {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+
+        names
+    }
+}
+
+/// Source wrapper appending the name of every variable looked up through it to a [`ReadLog`].
+struct Recorder {
+    inner: Box<dyn EnvSource>,
+    log:   ReadLog,
+}
+
+impl EnvSource for Recorder {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.log.0.borrow_mut().push(name.to_string());
+        self.inner.var_os(name)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<String> {
+        self.inner.read_file(path)
+    }
+}
+
+/// Accessor for the environment variables.
+///
+/// `Env` reads environment variables from an [`EnvSource`] (the process environment by default)
+/// and offers a couple of helpers to extract the values that need parsing (such as the GitHub
+/// Actions `GITHUB_REF`). Pre-compiled regular expressions used by those helpers are cached in the
+/// struct, so an `Env` is meant to be created once with [`Env::new`] (or [`Env::with_source`]) and
+/// reused.
+///
+/// Empty variables are treated as if they were unset, and values are never logged so that secrets
+/// like `COVERALLS_REPO_TOKEN` are not leaked.
+pub struct Env {
+    source:                         Box<dyn EnvSource>,
+    github_actions_branch_re:       Regex,
+    github_actions_pull_request_re: Regex,
+}
+
+impl Env {
+    /// Build a new `Env` reading the process environment, compiling the regular expressions used by
+    /// the helper methods.
+    pub fn new() -> Env {
+        Env::with_source(ProcessEnv)
+    }
+
+    /// Build a new `Env` reading its variables from `source` instead of the process environment.
+    ///
+    /// ```rust
+    /// use coveralls::Env;
+    /// use std::collections::HashMap;
+    ///
+    /// let vars = HashMap::from([(String::from("CI_NAME"), String::from("circleci"))]);
+    /// let env = Env::with_source(vars);
+    ///
+    /// assert_eq!(env.get_var("CI_NAME").unwrap().as_deref(), Some("circleci"));
+    /// ```
+    pub fn with_source(source: impl EnvSource + 'static) -> Env {
+        Env {
+            source:                         Box::new(source),
+            github_actions_branch_re:       Regex::new(r"^refs/(?:heads|tags)/(.+)$").expect("Bad regex"),
+            github_actions_pull_request_re: Regex::new(r"^refs/pull/(.+)$").expect("Bad regex"),
+        }
+    }
+
+    /// Add `source` below the current source: it is only read for the variables that the current
+    /// source does not define.
+    ///
+    /// This is how a dotenv file is layered under the process environment.
+    pub fn with_fallback(self, source: impl EnvSource + 'static) -> Env {
+        self.map_source(|inner| Box::new(LayeredEnv::new(inner).with_fallback(source)))
+    }
+
+    /// Wrap the source so that the name of every variable looked up is appended to the returned log.
+    pub(crate) fn record(self) -> (Env, ReadLog) {
+        let log = ReadLog::default();
+        let recorder_log = log.clone();
+        let env = self.map_source(|inner| {
+            Box::new(Recorder {
+                inner,
+                log: recorder_log,
+            })
+        });
+
+        (env, log)
+    }
+
+    /// Replace the source by the result of `f`, typically a wrapper around the current source.
+    fn map_source(self, f: impl FnOnce(Box<dyn EnvSource>) -> Box<dyn EnvSource>) -> Env {
+        Env {
+            source: f(self.source),
+            ..self
+        }
+    }
+
+    /// Read the environment variable `name`.
+    ///
+    /// Returns `Ok(None)` when the variable is unset *or* set to an empty string. Only the presence
+    /// of the variable is traced, never its value.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the variable holds non-Unicode data.
+    pub fn get_var(&self, name: &str) -> Result<Option<String>> {
+        let value = match self.source.var_os(name) {
+            None => None,
+            Some(s) => match s.into_string() {
+                Ok(v) => (!v.is_empty()).then_some(v),
+                Err(err) => {
+                    return Err(Error::new(ErrorKind::Other, SimpleError::new(format!("{:?}", err))));
+                }
+            },
+        };
+
+        // Note: only the presence is traced, never the value (e.g. COVERALLS_REPO_TOKEN is a secret).
+        trace!(
+            "Environment variable `{name}` is {}",
+            if value.is_some() { "set" } else { "unset" }
+        );
+
+        Ok(value)
+    }
+
+    /// Read the file at `path`, given by a variable, through the source (see
+    /// [`EnvSource::read_file`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the file cannot be read.
+    pub(crate) fn read_file(&self, path: &Path) -> Result<String> {
+        self.source.read_file(path)
+    }
+
+    /// Extract the branch (or tag) name from the GitHub Actions environment.
+    ///
+    /// The name is parsed out of `GITHUB_REF` (`refs/heads/<name>` or `refs/tags/<name>`); when it
+    /// does not match, `GITHUB_HEAD_REF` is used as a fallback, which is set for pull requests.
+    pub(crate) fn get_github_actions_branch(&self) -> Result<Option<String>> {
+        if let Some(github_ref) = self.get_var("GITHUB_REF")? {
+            if let Some(captures) = self.github_actions_branch_re.captures(&github_ref) {
+                return Ok(Some(captures[1].to_string()));
+            }
+        }
+
+        self.get_var("GITHUB_HEAD_REF")
+    }
+
+    /// Extract the pull request number from the GitHub Actions environment.
+    ///
+    /// The number is parsed out of `GITHUB_REF` when it has the `refs/pull/<number>/...` shape;
+    /// returns `Ok(None)` otherwise.
+    pub(crate) fn get_github_actions_pull_request(&self) -> Result<Option<String>> {
+        if let Some(github_ref) = self.get_var("GITHUB_REF")? {
+            if let Some(captures) = self.github_actions_pull_request_re.captures(&github_ref) {
+                return Ok(Some(captures[1].to_string()));
+            }
+        }
+
+        Ok(None)
+    }
+}
@@ -0,0 +1,161 @@
+//! Capture of the CI environment into a replayable JSON snapshot.
+
+use super::{Env, EnvSource};
+use crate::{config::Config, helpers};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use simple_error::SimpleError;
+use log::debug;
+use std::{
+    collections::BTreeMap,
+    ffi::OsString,
+    io::{Error, ErrorKind, Read, Result, Write},
+    path::Path,
+};
+
+/// Variables whose value is a secret, masked in a snapshot.
+const SECRET_VARIABLES: &[&str] = &["COVERALLS_REPO_TOKEN", "GITHUB_TOKEN"];
+
+/// Variable giving the path of the GitHub Actions event payload, embedded in a snapshot.
+const GITHUB_EVENT_PATH: &str = "GITHUB_EVENT_PATH";
+
+/// Variables read by the workflow itself, outside of the configuration loaders.
+const WORKFLOW_VARIABLES: &[&str] = &["COVERALLS_ENV_FILE", "GITHUB_STEP_SUMMARY"];
+
+/// The variables of a CI environment that are relevant to `coveralls`, as recorded by
+/// [`EnvSnapshot::capture`].
+///
+/// A snapshot holds every variable that the service detection or any service loader reads and
+/// that is set, with the secrets (such as `COVERALLS_REPO_TOKEN`) masked. The pull request of the
+/// GitHub Actions event payload is embedded too, the secrets masked the same way, and read back in
+/// place of the file at `GITHUB_EVENT_PATH`. Saved as JSON on a CI runner, it can be loaded back
+/// anywhere and used as an [`EnvSource`] to replay the configuration detection, for instance to
+/// understand why a branch is wrong without access to the runner.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct EnvSnapshot {
+    variables: BTreeMap<String, String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    masked: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    github_event: Option<Value>,
+}
+
+/// Mask the occurrences of the `secrets` in the strings of `value`.
+fn mask_strings(value: &mut Value, secrets: &[String]) {
+    match value {
+        Value::String(s) => {
+            for secret in secrets {
+                if s.contains(secret.as_str()) {
+                    *s = s.replace(secret.as_str(), &helpers::mask_secret(secret));
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| mask_strings(item, secrets)),
+        Value::Object(fields) => fields.values_mut().for_each(|field| mask_strings(field, secrets)),
+        _ => {}
+    }
+}
+
+impl EnvSnapshot {
+    /// Record the relevant variables of `env`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if a variable holds non-Unicode data.
+    pub fn capture(env: Env) -> Result<Self> {
+        let (env, log) = env.record();
+
+        Config::probe_environment(&env)?;
+
+        for name in WORKFLOW_VARIABLES {
+            env.get_var(name)?;
+        }
+
+        let mut snapshot = Self::default();
+        let mut secrets = vec![];
+        let names = log.names_since(0);
+
+        debug!("{} variable(s) read while probing the environment", names.len());
+
+        for name in names {
+            if let Some(value) = env.get_var(&name)? {
+                let value = if SECRET_VARIABLES.contains(&name.as_str()) {
+                    snapshot.masked.push(name.clone());
+
+                    let masked = helpers::mask_secret(&value);
+
+                    secrets.push(value);
+                    masked
+                } else {
+                    value
+                };
+
+                snapshot.variables.insert(name, value);
+            }
+        }
+
+        snapshot.github_event = Config::probe_github_event(&env)?;
+
+        if let Some(event) = &mut snapshot.github_event {
+            debug!("Embedding the GitHub Actions event payload");
+
+            mask_strings(event, &secrets);
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Parse a snapshot previously written with [`EnvSnapshot::to_writer`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if reading fails or if the data is not a valid snapshot.
+    pub fn from_reader<R: Read>(rdr: R) -> Result<Self> {
+        Ok(serde_json::from_reader(rdr)?)
+    }
+
+    /// Write the snapshot as pretty-printed JSON.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if writing fails.
+    pub fn to_writer<W: Write>(&self, mut writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)?;
+
+        writer.flush()
+    }
+
+    /// The recorded variables, by name.
+    pub fn variables(&self) -> &BTreeMap<String, String> {
+        &self.variables
+    }
+
+    /// The names of the recorded variables whose value was masked.
+    pub fn masked(&self) -> &[String] {
+        &self.masked
+    }
+}
+
+impl EnvSource for EnvSnapshot {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.variables.get(name).map(OsString::from)
+    }
+
+    /// Read the embedded event payload in place of the file at `GITHUB_EVENT_PATH`; no other file
+    /// is recorded.
+    fn read_file(&self, path: &Path) -> Result<String> {
+        match &self.github_event {
+            Some(event) if self.variables.get(GITHUB_EVENT_PATH).is_some_and(|v| Path::new(v) == path) => {
+                Ok(serde_json::to_string(event)?)
+            }
+            _ => {
+                let msg = format!("The file `{}` is not recorded in the snapshot", path.display());
+
+                Err(Error::new(ErrorKind::NotFound, SimpleError::new(msg)))
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+//! The [`EnvSource`] abstraction and its in-process implementations.
+
+use std::{collections::HashMap, env::var_os, ffi::OsString, fs, io::Result, path::Path};
+
+/// A source of environment variables read by an [`Env`](super::Env).
+///
+/// The process environment ([`ProcessEnv`]) is the default source; an in-memory
+/// `HashMap<String, String>`, a [`DotEnv`](super::DotEnv) file or a [`LayeredEnv`] can be used
+/// instead to drive the configuration deterministically, for instance from tests or from a tool
+/// embedding this crate.
+pub trait EnvSource {
+    /// Return the raw value of the variable `name`, or `None` when it is unset.
+    fn var_os(&self, name: &str) -> Option<OsString>;
+
+    /// Read the file at `path`, given by a variable (such as the GitHub Actions event payload at
+    /// `GITHUB_EVENT_PATH`); the file system is read by default.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the file cannot be read.
+    fn read_file(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path)
+    }
+}
+
+/// The environment variables of the current process.
+pub struct ProcessEnv;
+
+impl EnvSource for ProcessEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        var_os(name)
+    }
+}
+
+impl EnvSource for HashMap<String, String> {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.get(name).map(OsString::from)
+    }
+}
+
+impl<S: EnvSource + ?Sized> EnvSource for Box<S> {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        (**self).var_os(name)
+    }
+
+    fn read_file(&self, path: &Path) -> Result<String> {
+        (**self).read_file(path)
+    }
+}
+
+/// A stack of sources, looked up in order until one of them defines the variable.
+///
+/// ```rust
+/// use coveralls::{EnvSource, LayeredEnv};
+/// use std::collections::HashMap;
+///
+/// let overrides = HashMap::from([(String::from("CI_NAME"), String::from("travis-ci"))]);
+/// let defaults = HashMap::from([
+///     (String::from("CI_NAME"), String::from("circleci")),
+///     (String::from("CI_JOB_ID"), String::from("42")),
+/// ]);
+///
+/// let env = LayeredEnv::new(overrides).with_fallback(defaults);
+///
+/// assert_eq!(env.var_os("CI_NAME").unwrap(), "travis-ci");
+/// assert_eq!(env.var_os("CI_JOB_ID").unwrap(), "42");
+/// ```
+pub struct LayeredEnv {
+    layers: Vec<Box<dyn EnvSource>>,
+}
+
+impl LayeredEnv {
+    /// Start a stack whose top (highest precedence) layer is `source`.
+    pub fn new(source: impl EnvSource + 'static) -> Self {
+        Self {
+            layers: vec![Box::new(source)],
+        }
+    }
+
+    /// Add `source` below the existing layers: it is only looked up for the variables none of them
+    /// defines.
+    pub fn with_fallback(mut self, source: impl EnvSource + 'static) -> Self {
+        self.layers.push(Box::new(source));
+        self
+    }
+}
+
+impl EnvSource for LayeredEnv {
+    fn var_os(&self, name: &str) -> Option<OsString> {
+        self.layers.iter().find_map(|layer| layer.var_os(name))
+    }
+
+    /// Read the file from the first layer able to, returning the error of the last one otherwise.
+    fn read_file(&self, path: &Path) -> Result<String> {
+        let mut res = Err(std::io::Error::from(std::io::ErrorKind::NotFound));
+
+        for layer in &self.layers {
+            res = layer.read_file(path);
+
+            if res.is_ok() {
+                break;
+            }
+        }
+
+        res
+    }
+}
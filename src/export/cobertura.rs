@@ -0,0 +1,237 @@
+//! Writer for the Cobertura XML format (as read by GitLab merge request widgets, Jenkins, ...).
+
+use crate::coverage::{Branch, Coverage, SourceFile};
+use std::{
+    collections::BTreeMap,
+    io::{Result, Write},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Escape the characters that are not allowed verbatim in an XML attribute value.
+fn escape(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+
+    for c in value.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            _ => res.push(c),
+        }
+    }
+
+    res
+}
+
+/// Ratio of `covered` over `valid`, formatted as Cobertura expects; an empty set counts as fully
+/// covered.
+fn rate(covered: usize, valid: usize) -> String {
+    if valid == 0 {
+        String::from("1")
+    } else {
+        format!("{:.4}", covered as f64 / valid as f64)
+    }
+}
+
+/// Split a source file name into its package (parent directory, `.` for the root) and class name.
+fn split_name(name: &str) -> (&str, &str) {
+    match name.rsplit_once('/') {
+        Some(("", file)) => (".", file),
+        Some((dir, file)) => (dir, file),
+        None => (".", name),
+    }
+}
+
+/// The branches of `source` on a relevant line: a branch on a line that is not relevant, or past the
+/// end of the file, has no `line` element to carry its condition coverage, so it is left out.
+fn reported_branches(source: &SourceFile) -> impl Iterator<Item = &Branch> {
+    source.branches.iter().filter(|branch| {
+        branch
+            .line
+            .checked_sub(1)
+            .and_then(|idx| source.coverage.get(idx))
+            .is_some_and(Option::is_some)
+    })
+}
+
+/// Totals of a group of source files, used for the `coverage` and `package` elements.
+#[derive(Default)]
+struct Totals {
+    lines_valid:      usize,
+    lines_covered:    usize,
+    branches_valid:   usize,
+    branches_covered: usize,
+}
+
+impl Totals {
+    /// Add the counts of `source` to the totals.
+    fn add(&mut self, source: &SourceFile) {
+        self.lines_valid += source.lines_valid();
+        self.lines_covered += source.lines_covered();
+
+        for branch in reported_branches(source) {
+            self.branches_valid += 1;
+            if branch.hits > 0 {
+                self.branches_covered += 1;
+            }
+        }
+    }
+
+    /// The `line-rate` attribute value.
+    fn line_rate(&self) -> String {
+        rate(self.lines_covered, self.lines_valid)
+    }
+
+    /// The `branch-rate` attribute value.
+    fn branch_rate(&self) -> String {
+        rate(self.branches_covered, self.branches_valid)
+    }
+}
+
+/// Write the `class` element of a single source file.
+fn write_class<W: Write>(writer: &mut W, source: &SourceFile) -> Result<()> {
+    let (_, class_name) = split_name(&source.name);
+    let mut totals = Totals::default();
+
+    totals.add(source);
+
+    // Branch counts per line: (valid, covered).
+    let mut branches = BTreeMap::<usize, (usize, usize)>::new();
+
+    for branch in reported_branches(source) {
+        let entry = branches.entry(branch.line).or_default();
+
+        entry.0 += 1;
+        if branch.hits > 0 {
+            entry.1 += 1;
+        }
+    }
+
+    write!(
+        writer,
+        r#"        <class name="{}" filename="{}""#,
+        escape(class_name),
+        escape(&source.name),
+    )?;
+
+    writeln!(
+        writer,
+        r#" line-rate="{}" branch-rate="{}" complexity="0">"#,
+        totals.line_rate(),
+        totals.branch_rate(),
+    )?;
+
+    writeln!(writer, "          <methods/>")?;
+    writeln!(writer, "          <lines>")?;
+
+    for (idx, hits) in source.coverage.iter().enumerate() {
+        let Some(hits) = hits else {
+            continue;
+        };
+
+        let number = idx + 1;
+
+        match branches.get(&number) {
+            Some((valid, covered)) => {
+                let percent = covered * 100 / valid;
+
+                write!(
+                    writer,
+                    r#"            <line number="{number}" hits="{hits}" branch="true""#
+                )?;
+                writeln!(writer, r#" condition-coverage="{percent}% ({covered}/{valid})"/>"#)?;
+            }
+            None => {
+                writeln!(
+                    writer,
+                    r#"            <line number="{number}" hits="{hits}" branch="false"/>"#
+                )?;
+            }
+        }
+    }
+
+    writeln!(writer, "          </lines>")?;
+    writeln!(writer, "        </class>")
+}
+
+/// Write `coverage` to `writer` as a Cobertura XML document.
+///
+/// Source files are grouped into packages by parent directory, and every relevant line becomes a
+/// `line` element; lines carrying branch data are flagged with their condition coverage. The branch
+/// totals only count the branches of these lines, so that they agree with the condition coverages.
+pub(crate) fn write<W: Write>(coverage: &Coverage, mut writer: W) -> Result<()> {
+    let mut packages = BTreeMap::<&str, Vec<&SourceFile>>::new();
+    let mut totals = Totals::default();
+
+    for source in &coverage.source_files {
+        let (package, _) = split_name(&source.name);
+
+        packages.entry(package).or_default().push(source);
+        totals.add(source);
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+
+    writeln!(writer, r#"<?xml version="1.0" ?>"#)?;
+    writeln!(
+        writer,
+        r#"<!DOCTYPE coverage SYSTEM "http://cobertura.sourceforge.net/xml/coverage-04.dtd">"#
+    )?;
+
+    write!(
+        writer,
+        r#"<coverage line-rate="{}" branch-rate="{}" lines-covered="{}" lines-valid="{}""#,
+        totals.line_rate(),
+        totals.branch_rate(),
+        totals.lines_covered,
+        totals.lines_valid,
+    )?;
+
+    writeln!(
+        writer,
+        r#" branches-covered="{}" branches-valid="{}" complexity="0" version="{}" timestamp="{timestamp}">"#,
+        totals.branches_covered,
+        totals.branches_valid,
+        env!("CARGO_PKG_VERSION"),
+    )?;
+
+    writeln!(writer, "  <sources>")?;
+    writeln!(writer, "    <source>.</source>")?;
+    writeln!(writer, "  </sources>")?;
+    writeln!(writer, "  <packages>")?;
+
+    for (package, sources) in packages {
+        let mut package_totals = Totals::default();
+
+        for source in &sources {
+            package_totals.add(source);
+        }
+
+        writeln!(
+            writer,
+            r#"    <package name="{}" line-rate="{}" branch-rate="{}" complexity="0">"#,
+            escape(package),
+            package_totals.line_rate(),
+            package_totals.branch_rate(),
+        )?;
+
+        writeln!(writer, "      <classes>")?;
+
+        for source in sources {
+            write_class(&mut writer, source)?;
+        }
+
+        writeln!(writer, "      </classes>")?;
+        writeln!(writer, "    </package>")?;
+    }
+
+    writeln!(writer, "  </packages>")?;
+    writeln!(writer, "</coverage>")?;
+
+    writer.flush()
+}
@@ -0,0 +1,49 @@
+//! Writer for the LCOV tracefile format (as read by `genhtml`, SonarQube, IDE gutters, ...).
+
+use crate::coverage::Coverage;
+use std::io::{Result, Write};
+
+/// Write `coverage` to `writer` as an LCOV tracefile.
+///
+/// Every source file becomes one `SF:` record with a `DA:` line per relevant line and, when the
+/// report carries branch data, a `BRDA:` line per branch followed by the `BRF`/`BRH` totals.
+pub(crate) fn write<W: Write>(coverage: &Coverage, mut writer: W) -> Result<()> {
+    writeln!(writer, "TN:")?;
+
+    for source in &coverage.source_files {
+        writeln!(writer, "SF:{}", source.name)?;
+
+        for (idx, hits) in source.coverage.iter().enumerate() {
+            if let Some(hits) = hits {
+                writeln!(writer, "DA:{},{hits}", idx + 1)?;
+            }
+        }
+
+        let mut branches_found = 0;
+        let mut branches_hit = 0;
+
+        for branch in &source.branches {
+            writeln!(
+                writer,
+                "BRDA:{},{},{},{}",
+                branch.line, branch.block, branch.branch, branch.hits
+            )?;
+
+            branches_found += 1;
+            if branch.hits > 0 {
+                branches_hit += 1;
+            }
+        }
+
+        if branches_found > 0 {
+            writeln!(writer, "BRF:{branches_found}")?;
+            writeln!(writer, "BRH:{branches_hit}")?;
+        }
+
+        writeln!(writer, "LF:{}", source.lines_valid())?;
+        writeln!(writer, "LH:{}", source.lines_covered())?;
+        writeln!(writer, "end_of_record")?;
+    }
+
+    writer.flush()
+}
@@ -0,0 +1,141 @@
+//! Writer for a Markdown coverage summary, suitable for a pull request comment or a GitHub Actions
+//! job summary.
+
+use crate::{
+    coverage::{Coverage, SourceFile},
+    helpers::percent,
+};
+use std::{
+    collections::HashMap,
+    io::{Result, Write},
+};
+
+/// Maximum number of files listed in the "top uncovered files" section.
+const TOP_UNCOVERED: usize = 10;
+
+/// Escape the characters that would break a Markdown table cell.
+fn escape(value: &str) -> String {
+    value.replace('|', "\\|")
+}
+
+/// Render the difference between two percentages, or an em dash without baseline.
+fn delta(current: f64, baseline: Option<f64>) -> String {
+    match baseline {
+        Some(baseline) => format!("{:+.2}%", current - baseline),
+        None => String::from("—"),
+    }
+}
+
+/// Coverage percentage of a single source file.
+fn file_percent(source: &SourceFile) -> f64 {
+    percent(source.lines_covered(), source.lines_valid())
+}
+
+/// Write a Markdown summary of `coverage` to `writer`.
+///
+/// The summary holds the total line (and branch, when available) coverage, a "changed files"
+/// section listing the files that are new or whose digest differs from `baseline`, and the files
+/// with the most uncovered lines. Deltas are only rendered when a `baseline` report is given.
+pub(crate) fn write<W: Write>(coverage: &Coverage, baseline: Option<&Coverage>, mut writer: W) -> Result<()> {
+    let (valid, covered) = coverage.line_totals();
+    let total = percent(covered, valid);
+    let baseline_total = baseline.map(|baseline| {
+        let (valid, covered) = baseline.line_totals();
+
+        percent(covered, valid)
+    });
+
+    writeln!(writer, "## Coverage report")?;
+    writeln!(writer)?;
+    writeln!(writer, "| | Lines | Covered | Coverage | Δ |")?;
+    writeln!(writer, "| --- | ---: | ---: | ---: | ---: |")?;
+    writeln!(
+        writer,
+        "| **Total** | {valid} | {covered} | **{total:.2}%** | {} |",
+        delta(total, baseline_total)
+    )?;
+
+    let (branches_valid, branches_covered) = coverage.branch_totals();
+
+    if branches_valid > 0 {
+        writeln!(writer)?;
+        writeln!(
+            writer,
+            "Branches: {branches_covered} of {branches_valid} covered ({:.2}%).",
+            percent(branches_covered, branches_valid)
+        )?;
+    }
+
+    if let Some(baseline) = baseline {
+        let previous = baseline
+            .source_files
+            .iter()
+            .map(|source| (source.name.as_str(), source))
+            .collect::<HashMap<_, _>>();
+
+        let changed = coverage
+            .source_files
+            .iter()
+            .filter_map(|source| match previous.get(source.name.as_str()) {
+                None => Some((source, None)),
+                Some(old) if old.source_digest != source.source_digest => Some((source, Some(file_percent(old)))),
+                Some(_) => None,
+            })
+            .collect::<Vec<_>>();
+
+        writeln!(writer)?;
+        writeln!(writer, "### Changed files")?;
+        writeln!(writer)?;
+
+        if changed.is_empty() {
+            writeln!(writer, "No source file changed since the baseline.")?;
+        } else {
+            writeln!(writer, "| File | Lines | Covered | Coverage | Δ |")?;
+            writeln!(writer, "| --- | ---: | ---: | ---: | ---: |")?;
+
+            for (source, old) in changed {
+                let current = file_percent(source);
+                let delta = match old {
+                    Some(old) => delta(current, Some(old)),
+                    None => String::from("new"),
+                };
+
+                writeln!(
+                    writer,
+                    "| `{}` | {} | {} | {current:.2}% | {delta} |",
+                    escape(&source.name),
+                    source.lines_valid(),
+                    source.lines_covered(),
+                )?;
+            }
+        }
+    }
+
+    let mut uncovered = coverage
+        .source_files
+        .iter()
+        .map(|source| (source, source.lines_valid() - source.lines_covered()))
+        .filter(|(_, missed)| *missed > 0)
+        .collect::<Vec<_>>();
+
+    uncovered.sort_by(|(a, a_missed), (b, b_missed)| b_missed.cmp(a_missed).then_with(|| a.name.cmp(&b.name)));
+
+    if !uncovered.is_empty() {
+        writeln!(writer)?;
+        writeln!(writer, "### Top uncovered files")?;
+        writeln!(writer)?;
+        writeln!(writer, "| File | Uncovered lines | Coverage |")?;
+        writeln!(writer, "| --- | ---: | ---: |")?;
+
+        for (source, missed) in uncovered.into_iter().take(TOP_UNCOVERED) {
+            writeln!(
+                writer,
+                "| `{}` | {missed} | {:.2}% |",
+                escape(&source.name),
+                file_percent(source)
+            )?;
+        }
+    }
+
+    writer.flush()
+}
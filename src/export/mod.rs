@@ -0,0 +1,10 @@
+//! Writers turning a [`Coverage`](crate::Coverage) report into other coverage formats.
+//!
+//! Once a report has been pruned and its paths rewritten by a
+//! [`CoverallsManager`](crate::CoverallsManager), it can be fanned out to the formats understood by
+//! other tools: the LCOV tracefile format ([`lcov`]) and the Cobertura XML format ([`cobertura`]). A
+//! human-readable [`markdown`] summary can be rendered as well, for pull request comments.
+
+pub(crate) mod cobertura;
+pub(crate) mod lcov;
+pub(crate) mod markdown;
@@ -1,12 +1,13 @@
-//! `GitFetcher` backend that shells out to the `git` command (default, no `libgit` feature).
+//! [`GitFetcher`] backend that shells out to the `git` command (always available).
 
-use super::LogInfos;
+use super::{super::head::SignatureStatus, GitFetcher, LogInfos};
 use simple_error::SimpleError;
-use log::trace;
+use log::{debug, trace};
 use std::{
-    io::{Result, Error, ErrorKind},
+    io::{Result, Error},
     process::Command,
     ffi::OsStr,
+    path::{Path, PathBuf},
 };
 
 /// Parse one line of `git remote -v` output into a `(name, url)` pair.
@@ -27,25 +28,79 @@ fn extract_remote(line: &str) -> Option<(String, String)> {
     }
 }
 
+/// Map the signature code of `git log --pretty=%G?` to a status, `None` for an unsigned commit.
+fn signature_status(code: &str) -> Option<SignatureStatus> {
+    match code {
+        "G" => Some(SignatureStatus::Good),
+        "B" => Some(SignatureStatus::Bad),
+        "U" => Some(SignatureStatus::UnknownValidity),
+        "X" => Some(SignatureStatus::Expired),
+        "Y" => Some(SignatureStatus::ExpiredKey),
+        "R" => Some(SignatureStatus::RevokedKey),
+        "E" => Some(SignatureStatus::Unverified),
+        _ => None,
+    }
+}
+
+/// Turn a reference name given by `git name-rev` or `git for-each-ref` into a branch name: the
+/// `remotes/<remote>/` prefix and the `~N`/`^N` ancestry suffix are dropped, and symbolic `HEAD`
+/// references are ignored.
+fn branch_of_ref(name: &str) -> Option<String> {
+    let name = name.trim();
+    let name = name.split(['~', '^']).next().unwrap_or(name);
+    let name = name.strip_prefix("refs/").unwrap_or(name);
+    let name = match name.strip_prefix("remotes/") {
+        Some(remote) => remote.split_once('/')?.1,
+        None => name.strip_prefix("heads/").unwrap_or(name),
+    };
+
+    (!name.is_empty() && name != "HEAD" && name != "undefined").then(|| name.to_string())
+}
+
 /// Collects Git metadata by invoking the `git` executable as a subprocess.
-pub(in super::super) struct GitFetcher;
+pub(super) struct CommandFetcher {
+    /// Top-level directory of the working tree, every command is run from.
+    root: PathBuf,
+}
+
+impl CommandFetcher {
+    /// Create a fetcher for the repository enclosing `dir`, be it a regular repository, a linked
+    /// worktree or a submodule.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if `dir` is not inside a Git working tree, or if `git` cannot be
+    /// run.
+    pub(super) fn new(dir: &Path) -> Result<Self> {
+        let root = Self::run_in(dir, vec!["rev-parse", "--show-toplevel"])?;
+        let root = PathBuf::from(root.trim());
+
+        debug!("Using the Git repository at `{}`", root.display());
 
-impl GitFetcher {
-    /// Create a new fetcher. The `git` binary is only invoked lazily by the other methods.
-    pub(in super::super) fn new() -> Result<Self> {
-        Ok(GitFetcher)
+        Ok(CommandFetcher {
+            root,
+        })
     }
 
-    /// Run `git` with the given arguments and return its standard output.
+    /// Run `git` with the given arguments from the working tree and return its standard output.
     ///
     /// # Errors
     ///
     /// Returns an [`std::io::Error`] if the process cannot be spawned, exits with a non-zero status
     /// or produces non-UTF-8 output.
     fn run_command(&self, args: Vec<&str>) -> Result<String> {
-        trace!("Running Git command: git {}", args.join(" "));
+        Self::run_in(&self.root, args)
+    }
+
+    /// Run `git -C dir` with the given arguments and return its standard output.
+    fn run_in(dir: &Path, args: Vec<&str>) -> Result<String> {
+        trace!("Running Git command: git -C {} {}", dir.display(), args.join(" "));
 
-        let res = Command::new("git").args(args.into_iter().map(OsStr::new)).output()?;
+        let res = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(args.into_iter().map(OsStr::new))
+            .output()?;
 
         if !res.status.success() {
             let out = String::from_utf8_lossy(res.stdout.as_slice());
@@ -55,53 +110,136 @@ impl GitFetcher {
                 res.status, out, err
             );
 
-            return Err(Error::new(ErrorKind::Other, SimpleError::new(msg)));
+            return Err(Error::other(SimpleError::new(msg)));
         }
 
         match String::from_utf8(res.stdout) {
             Ok(v) => Ok(v),
-            Err(err) => Err(Error::new(ErrorKind::Other, err)),
+            Err(err) => Err(Error::other(err)),
         }
     }
 
+    /// Return the information about the commit `rev` (`git log -1`).
+    ///
+    /// The fields are separated by NUL bytes, the full message (`%B`) coming last as it spans
+    /// several lines.
+    fn log(&self, rev: &str) -> Result<LogInfos> {
+        let res = self.run_command(vec![
+            "--no-pager",
+            "log",
+            "-1",
+            "--pretty=format:%H%x00%aN%x00%ae%x00%aI%x00%cN%x00%ce%x00%cI%x00%P%x00%B",
+            rev,
+        ])?;
+
+        let mut fields = res.splitn(9, '\0');
+        let mut next = || {
+            let s = fields.next().unwrap_or_default().trim();
+
+            (!s.is_empty()).then(|| s.to_string())
+        };
+
+        Ok(LogInfos {
+            id:              next(),
+            author_name:     next(),
+            author_email:    next(),
+            author_date:     next(),
+            committer_name:  next(),
+            committer_email: next(),
+            committer_date:  next(),
+            parents:         next()
+                .map(|v| v.split_whitespace().map(String::from).collect())
+                .unwrap_or_default(),
+            message:         next(),
+        })
+    }
+}
+
+impl GitFetcher for CommandFetcher {
     /// Return the current branch name (`git rev-parse --abbrev-ref HEAD`), or `None` in detached
-    /// `HEAD` state.
-    pub(in super::super) fn get_branch(&self) -> Result<Option<String>> {
+    /// `HEAD` state (where `git` answers the literal `HEAD`).
+    fn get_branch(&self) -> Result<Option<String>> {
         let res = self
             .run_command(vec!["rev-parse", "--abbrev-ref", "HEAD"])?
             .trim()
             .to_string();
 
-        Ok((!res.is_empty()).then_some(res))
+        Ok((!res.is_empty() && res != "HEAD").then_some(res))
+    }
+
+    /// Tell whether the repository is a shallow clone, whose history is truncated.
+    fn is_shallow(&self) -> Result<bool> {
+        Ok(self.run_command(vec!["rev-parse", "--is-shallow-repository"])?.trim() == "true")
+    }
+
+    /// Guess the branch of a detached `HEAD`: the closest local or remote branch found by
+    /// `git name-rev`, or else the first remote branch containing the commit. Failures (as in a
+    /// shallow clone whose history does not reach any branch) are not errors, the branch is just
+    /// unknown.
+    fn guess_branch(&self) -> Result<Option<String>> {
+        let name_rev = self.run_command(vec![
+            "name-rev",
+            "--name-only",
+            "--no-undefined",
+            "--refs=refs/heads/*",
+            "--refs=refs/remotes/*",
+            "HEAD",
+        ]);
+
+        match name_rev.as_deref().map(branch_of_ref) {
+            Ok(Some(branch)) => return Ok(Some(branch)),
+            Ok(None) => {}
+            Err(err) => debug!("`git name-rev` found no branch for HEAD: {err}"),
+        }
+
+        let contains = self.run_command(vec![
+            "for-each-ref",
+            "--contains",
+            "HEAD",
+            "--format=%(refname)",
+            "refs/remotes",
+        ]);
+
+        match contains {
+            Ok(refs) => Ok(refs.lines().find_map(branch_of_ref)),
+            Err(err) => {
+                debug!("No remote branch found containing HEAD: {err}");
+
+                Ok(None)
+            }
+        }
     }
 
     /// Return the information about the `HEAD` commit (`git log -1`).
-    pub(in super::super) fn get_log(&self) -> Result<LogInfos> {
-        let res = self.run_command(vec![
-            "--no-pager",
-            "log",
-            "-1",
-            "--pretty=format:%H%n%aN%n%ae%n%cN%n%ce%n%s",
-        ])?;
+    fn get_log(&self) -> Result<LogInfos> {
+        self.log("HEAD")
+    }
 
-        let mut fields = res.split('\n').map(|s| {
-            let s = s.trim();
+    /// Return the information about the commit `id`, or `None` when `git rev-parse --verify` does not
+    /// find it.
+    fn get_commit_log(&self, id: &str) -> Result<Option<LogInfos>> {
+        let commit = format!("{id}^{{commit}}");
 
-            (!s.is_empty()).then(|| s.to_string())
-        });
-
-        Ok(LogInfos::new(
-            fields.next().flatten(),
-            fields.next().flatten(),
-            fields.next().flatten(),
-            fields.next().flatten(),
-            fields.next().flatten(),
-            fields.next().flatten(),
-        ))
+        match self.run_command(vec!["rev-parse", "--verify", "--quiet", &commit]) {
+            Ok(id) => Ok(Some(self.log(id.trim())?)),
+            Err(err) => {
+                debug!("The commit `{id}` is not in the repository: {err}");
+
+                Ok(None)
+            }
+        }
+    }
+
+    /// Return the status of the signature of the `HEAD` commit (`git log -1 --pretty=%G?`), which
+    /// runs `gpg` to verify it.
+    fn get_signature(&self) -> Result<Option<SignatureStatus>> {
+        let res = self.run_command(vec!["--no-pager", "log", "-1", "--pretty=format:%G?"])?;
+
+        Ok(signature_status(res.trim()))
     }
 
     /// Return the list of `(name, url)` remotes (`git remote -v`), or `None` when there is none.
-    pub(in super::super) fn get_remotes(&self) -> Result<Option<Vec<(String, String)>>> {
+    fn get_remotes(&self) -> Result<Option<Vec<(String, String)>>> {
         let res = self
             .run_command(vec!["remote", "-v"])?
             .trim()
@@ -0,0 +1,186 @@
+//! [`GitFetcher`] backend that reads the repository in-process through [`gix`], a pure Rust
+//! implementation of Git needing neither `libgit2` nor a `git` binary (the `gitoxide` feature).
+
+use super::{super::head::SignatureStatus, GitFetcher, LogInfos};
+use gix::{Commit, ObjectId, Repository, date::time::format::ISO8601_STRICT, remote::Direction};
+use log::{debug, trace};
+use std::{
+    io::{Result, Error, ErrorKind},
+    path::Path,
+};
+
+/// Convert a [`gix`] result into an [`std::io::Error`], returning early on failure.
+macro_rules! checked {
+    ($r:expr) => {
+        match $r {
+            Ok(v) => v,
+            Err(err) => {
+                return Err(Error::new(ErrorKind::Other, err));
+            }
+        }
+    };
+}
+
+/// Collects Git metadata by reading the repository in-process with [`gix`].
+pub(super) struct GitoxideFetcher {
+    repo:        Repository,
+    head_id:     ObjectId,
+    branch_name: Option<String>,
+}
+
+impl GitoxideFetcher {
+    /// Open the repository enclosing `dir`, be it a regular repository, a linked worktree or a
+    /// submodule, and resolve its `HEAD`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if no Git repository encloses `dir` or if `HEAD` cannot be
+    /// resolved.
+    pub(super) fn new(dir: &Path) -> Result<Self> {
+        let repo = checked! { gix::discover(dir) };
+
+        debug!("Using the Git repository at `{}`", repo.git_dir().display());
+
+        let head_id = checked! { repo.head_id() }.detach();
+        let branch_name = checked! { repo.head_name() }.map(|name| name.as_bstr().to_string());
+
+        Ok(GitoxideFetcher {
+            repo,
+            head_id,
+            branch_name,
+        })
+    }
+}
+
+impl GitFetcher for GitoxideFetcher {
+    /// Return the current branch name, stripped of its `refs/heads/` prefix, or `None` in detached
+    /// `HEAD` state.
+    fn get_branch(&self) -> Result<Option<String>> {
+        Ok(self
+            .branch_name
+            .as_deref()
+            .and_then(|name| name.strip_prefix("refs/heads/"))
+            .map(String::from))
+    }
+
+    /// Tell whether the repository is a shallow clone, whose history is truncated.
+    fn is_shallow(&self) -> Result<bool> {
+        Ok(self.repo.is_shallow())
+    }
+
+    /// Guess the branch of a detached `HEAD`: a local, then a remote, branch pointing at the commit,
+    /// or else the first one containing it. The history of a shallow clone may not reach any branch,
+    /// in which case the branch is just unknown.
+    fn guess_branch(&self) -> Result<Option<String>> {
+        let references = checked! { self.repo.references() };
+        let mut branches = vec![];
+
+        for (remote, iter) in [
+            (false, checked! { references.local_branches() }),
+            (true, checked! { references.remote_branches() }),
+        ] {
+            for reference in iter {
+                let mut reference = checked! { reference };
+                let name = reference.name().shorten().to_string();
+                let name = if remote {
+                    match name.split_once('/') {
+                        Some((_, name)) if name != "HEAD" => name.to_string(),
+                        _ => continue,
+                    }
+                } else {
+                    name
+                };
+
+                match reference.peel_to_id() {
+                    Ok(tip) => branches.push((tip.detach(), name)),
+                    Err(err) => debug!("Cannot resolve the tip of `{name}`: {err}"),
+                }
+            }
+        }
+
+        if let Some((_, name)) = branches.iter().find(|(tip, _)| *tip == self.head_id) {
+            return Ok(Some(name.clone()));
+        }
+
+        for (tip, name) in branches {
+            match self.repo.merge_base(tip, self.head_id) {
+                Ok(base) if base == self.head_id => return Ok(Some(name)),
+                Ok(_) => {}
+                Err(err) => debug!("Cannot tell whether `{name}` contains HEAD: {err}"),
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Return the information about the `HEAD` commit.
+    fn get_log(&self) -> Result<LogInfos> {
+        trace!("Reading HEAD commit {} via gitoxide", self.head_id);
+
+        commit_log(&checked! { self.repo.find_commit(self.head_id) })
+    }
+
+    /// Return the information about the commit `id`, or `None` when it cannot be resolved in the
+    /// repository.
+    fn get_commit_log(&self, id: &str) -> Result<Option<LogInfos>> {
+        trace!("Reading commit {id} via gitoxide");
+
+        let object = match self.repo.rev_parse_single(id) {
+            Ok(v) => checked! { v.object() },
+            Err(err) => {
+                debug!("The commit `{id}` is not in the repository: {err}");
+
+                return Ok(None);
+            }
+        };
+
+        Ok(Some(commit_log(&checked! { object.peel_to_commit() })?))
+    }
+
+    /// Return the status of the signature of the `HEAD` commit: gitoxide cannot verify signatures,
+    /// a signed commit is only known to be signed.
+    fn get_signature(&self) -> Result<Option<SignatureStatus>> {
+        let commit = checked! { self.repo.find_commit(self.head_id) };
+
+        Ok(checked! { commit.signature() }.map(|_| SignatureStatus::Unverified))
+    }
+
+    /// Return the list of `(name, url)` remotes of the repository, or `None` when there is none.
+    fn get_remotes(&self) -> Result<Option<Vec<(String, String)>>> {
+        let mut res = vec![];
+
+        for name in self.repo.remote_names() {
+            let remote = checked! { self.repo.find_remote(name.as_ref()) };
+
+            if let Some(url) = remote.url(Direction::Fetch) {
+                res.push((name.to_string(), url.to_bstring().to_string()));
+            }
+        }
+
+        Ok((!res.is_empty()).then_some(res))
+    }
+}
+
+/// Collect the information about `commit`.
+fn commit_log(commit: &Commit) -> Result<LogInfos> {
+    let author = checked! { commit.author() };
+    let committer = checked! { commit.committer() };
+    let message = checked! { commit.message_raw() };
+
+    Ok(LogInfos {
+        id:              Some(commit.id().to_string()),
+        author_name:     map_string(&author.name.to_string()),
+        author_email:    map_string(&author.email.to_string()),
+        author_date:     author.time().ok().map(|t| t.format(ISO8601_STRICT)),
+        committer_name:  map_string(&committer.name.to_string()),
+        committer_email: map_string(&committer.email.to_string()),
+        committer_date:  committer.time().ok().map(|t| t.format(ISO8601_STRICT)),
+        message:         map_string(&message.to_string()),
+        parents:         commit.parent_ids().map(|id| id.to_string()).collect(),
+    })
+}
+
+/// Turn a borrowed string into an owned one, mapping the empty string to `None`.
+fn map_string(s: &str) -> Option<String> {
+    (!s.is_empty()).then(|| String::from(s))
+}
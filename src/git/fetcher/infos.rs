@@ -1,40 +1,39 @@
 //! Backend-agnostic representation of the last commit read by a `GitFetcher`.
 
+use super::super::head::MessageFormat;
+
 /// Information about a single commit, as returned by a `GitFetcher`.
 ///
 /// Every field is optional because a backend may fail to provide it (for instance an empty commit
 /// message). This is an intermediate, backend-agnostic structure that the Git `HEAD` information is
-/// then filled from.
+/// then filled from: both backends store the full message, the subject being derived from it the
+/// same way for both, and dates in the strict ISO 8601 format.
 #[derive(Default)]
 pub(in super::super) struct LogInfos {
-    id:              Option<String>,
-    author_name:     Option<String>,
-    author_email:    Option<String>,
-    committer_name:  Option<String>,
-    committer_email: Option<String>,
-    message:         Option<String>,
+    pub(super) id:              Option<String>,
+    pub(super) author_name:     Option<String>,
+    pub(super) author_email:    Option<String>,
+    pub(super) author_date:     Option<String>,
+    pub(super) committer_name:  Option<String>,
+    pub(super) committer_email: Option<String>,
+    pub(super) committer_date:  Option<String>,
+    pub(super) message:         Option<String>,
+    pub(super) parents:         Vec<String>,
 }
 
-impl LogInfos {
-    /// Build a `LogInfos` from the individual commit fields.
-    pub(super) fn new(
-        id: Option<String>,
-        author_name: Option<String>,
-        author_email: Option<String>,
-        committer_name: Option<String>,
-        committer_email: Option<String>,
-        message: Option<String>,
-    ) -> Self {
-        Self {
-            id,
-            author_name,
-            author_email,
-            committer_name,
-            committer_email,
-            message,
-        }
-    }
+/// Extract the subject of a commit message: its first paragraph, on a single line (as `git log
+/// --pretty=%s` does).
+fn subject(message: &str) -> String {
+    message
+        .trim_start()
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
 
+impl LogInfos {
     /// The commit identifier (SHA).
     pub(in super::super) fn id(&self) -> Option<&str> {
         self.id.as_deref()
@@ -50,6 +49,11 @@ impl LogInfos {
         self.author_email.as_deref()
     }
 
+    /// The author date, in the strict ISO 8601 format.
+    pub(in super::super) fn author_date(&self) -> Option<&str> {
+        self.author_date.as_deref()
+    }
+
     /// The commit committer name.
     pub(in super::super) fn committer_name(&self) -> Option<&str> {
         self.committer_name.as_deref()
@@ -60,8 +64,25 @@ impl LogInfos {
         self.committer_email.as_deref()
     }
 
-    /// The commit message subject.
-    pub(in super::super) fn message(&self) -> Option<&str> {
-        self.message.as_deref()
+    /// The commit date, in the strict ISO 8601 format.
+    pub(in super::super) fn committer_date(&self) -> Option<&str> {
+        self.committer_date.as_deref()
+    }
+
+    /// The commit message, as its subject or in full (without trailing blank lines).
+    pub(in super::super) fn message(&self, format: MessageFormat) -> Option<String> {
+        let message = self.message.as_deref()?;
+        let message = match format {
+            MessageFormat::Subject => subject(message),
+            MessageFormat::Full => message.trim_end().to_string(),
+        };
+
+        (!message.is_empty()).then_some(message)
+    }
+
+    /// The identifiers of the parent commits, empty for a root commit or when the history is
+    /// truncated.
+    pub(in super::super) fn parents(&self) -> &[String] {
+        &self.parents
     }
 }
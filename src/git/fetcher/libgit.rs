@@ -1,13 +1,14 @@
-//! `GitFetcher` backend that reads the repository in-process through [`git2`] (the `libgit`
+//! [`GitFetcher`] backend that reads the repository in-process through [`git2`] (the `libgit`
 //! feature).
 
-use super::LogInfos;
+use super::{super::head::SignatureStatus, GitFetcher, LogInfos};
 use regex::Regex;
-use git2::{Oid, Repository, Error as GitError};
+use git2::{BranchType, Commit, ErrorCode, Oid, Repository, Time, Error as GitError};
 use simple_error::SimpleError;
-use log::trace;
+use log::{debug, trace};
 use std::{
     io::{Result, Error, ErrorKind},
+    path::Path,
     result::Result as StdResult,
 };
 
@@ -67,36 +68,42 @@ impl BranchInfos {
 }
 
 /// Collects Git metadata by reading the repository in-process with [`git2`].
-pub(in super::super) struct GitFetcher {
+pub(super) struct LibgitFetcher {
     repo:        Repository,
     head_id:     Oid,
     branch_name: Option<String>,
     branch_re:   Regex,
 }
 
-impl GitFetcher {
-    /// Open the repository in the current directory and resolve its `HEAD`.
+impl LibgitFetcher {
+    /// Open the repository enclosing `dir`, be it a regular repository, a linked worktree or a
+    /// submodule, and resolve its `HEAD`.
     ///
     /// # Errors
     ///
-    /// Returns an [`std::io::Error`] if the current directory is not a Git repository or if `HEAD`
-    /// cannot be resolved.
-    pub(in super::super) fn new() -> Result<Self> {
-        let repo = checked! { Repository::open(".") };
+    /// Returns an [`std::io::Error`] if no Git repository encloses `dir` or if `HEAD` cannot be
+    /// resolved.
+    pub(super) fn new(dir: &Path) -> Result<Self> {
+        let repo = checked! { Repository::discover(dir) };
+
+        debug!("Using the Git repository at `{}`", repo.path().display());
+
         let infos = BranchInfos::from_repo(&repo)?;
         let branch_re = Regex::new(r"^refs/heads/(.+)$").expect("Bad regex");
 
-        Ok(GitFetcher {
+        Ok(LibgitFetcher {
             repo,
             head_id: infos.head_id,
             branch_name: infos.branch_name,
             branch_re,
         })
     }
+}
 
+impl GitFetcher for LibgitFetcher {
     /// Return the current branch name, stripped of its `refs/heads/` prefix, or `None` in detached
     /// `HEAD` state.
-    pub(in super::super) fn get_branch(&self) -> Result<Option<String>> {
+    fn get_branch(&self) -> Result<Option<String>> {
         if let Some(name) = self.branch_name.as_ref() {
             if let Some(caps) = self.branch_re.captures(name) {
                 return Ok(Some(caps[1].to_string()));
@@ -106,26 +113,87 @@ impl GitFetcher {
         Ok(None)
     }
 
+    /// Tell whether the repository is a shallow clone, whose history is truncated.
+    fn is_shallow(&self) -> Result<bool> {
+        Ok(self.repo.is_shallow())
+    }
+
+    /// Guess the branch of a detached `HEAD`: a local, then a remote, branch pointing at the commit,
+    /// or else the first one containing it. The history of a shallow clone may not reach any branch,
+    /// in which case the branch is just unknown.
+    fn guess_branch(&self) -> Result<Option<String>> {
+        let mut branches = vec![];
+
+        for kind in [BranchType::Local, BranchType::Remote] {
+            for branch in checked! { self.repo.branches(Some(kind)) } {
+                let (branch, _) = checked! { branch };
+                let (Some(tip), Ok(Some(name))) = (branch.get().target(), branch.name()) else {
+                    continue;
+                };
+
+                let name = match kind {
+                    BranchType::Local => name,
+                    BranchType::Remote => match name.split_once('/') {
+                        Some((_, name)) if name != "HEAD" => name,
+                        _ => continue,
+                    },
+                };
+
+                branches.push((tip, name.to_string()));
+            }
+        }
+
+        if let Some((_, name)) = branches.iter().find(|(tip, _)| *tip == self.head_id) {
+            return Ok(Some(name.clone()));
+        }
+
+        for (tip, name) in branches {
+            match self.repo.graph_descendant_of(tip, self.head_id) {
+                Ok(true) => return Ok(Some(name)),
+                Ok(false) => {}
+                Err(err) => debug!("Cannot tell whether `{name}` contains HEAD: {err}"),
+            }
+        }
+
+        Ok(None)
+    }
+
     /// Return the information about the `HEAD` commit.
-    pub(in super::super) fn get_log(&self) -> Result<LogInfos> {
+    fn get_log(&self) -> Result<LogInfos> {
         trace!("Reading HEAD commit {} via libgit2", self.head_id);
 
-        let commit = checked! { self.repo.find_commit(self.head_id) };
-        let author = commit.author();
-        let committer = commit.committer();
-
-        Ok(LogInfos::new(
-            Some(commit.id().to_string()),
-            checked! { author.name().map(map_string) },
-            checked! { author.email().map(map_string) },
-            checked! { committer.name().map(map_string) },
-            checked! { committer.email().map(map_string) },
-            checked! { commit.message().map(map_string) },
-        ))
+        commit_log(&checked! { self.repo.find_commit(self.head_id) })
+    }
+
+    /// Return the information about the commit `id`, or `None` when the repository lacks it.
+    fn get_commit_log(&self, id: &str) -> Result<Option<LogInfos>> {
+        trace!("Reading commit {id} via libgit2");
+
+        let object = match self.repo.revparse_single(id) {
+            Ok(v) => v,
+            Err(err) if err.code() == ErrorCode::NotFound => {
+                debug!("The commit `{id}` is not in the repository: {err}");
+
+                return Ok(None);
+            }
+            Err(err) => return Err(Error::other(err)),
+        };
+
+        Ok(Some(commit_log(&checked! { object.peel_to_commit() })?))
+    }
+
+    /// Return the status of the signature of the `HEAD` commit: libgit2 cannot verify signatures,
+    /// a signed commit is only known to be signed.
+    fn get_signature(&self) -> Result<Option<SignatureStatus>> {
+        Ok(self
+            .repo
+            .extract_signature(&self.head_id, None)
+            .ok()
+            .map(|_| SignatureStatus::Unverified))
     }
 
     /// Return the list of `(name, url)` remotes of the repository, or `None` when there is none.
-    pub(in super::super) fn get_remotes(&self) -> Result<Option<Vec<(String, String)>>> {
+    fn get_remotes(&self) -> Result<Option<Vec<(String, String)>>> {
         let mut res = vec![];
         let remotes = checked! { self.repo.remotes() };
 
@@ -141,6 +209,24 @@ impl GitFetcher {
     }
 }
 
+/// Collect the information about `commit`.
+fn commit_log(commit: &Commit) -> Result<LogInfos> {
+    let author = commit.author();
+    let committer = commit.committer();
+
+    Ok(LogInfos {
+        id:              Some(commit.id().to_string()),
+        author_name:     checked! { author.name().map(map_string) },
+        author_email:    checked! { author.email().map(map_string) },
+        author_date:     Some(format_time(author.when())),
+        committer_name:  checked! { committer.name().map(map_string) },
+        committer_email: checked! { committer.email().map(map_string) },
+        committer_date:  Some(format_time(committer.when())),
+        message:         checked! { commit.message().map(map_string) },
+        parents:         commit.parent_ids().map(|id| id.to_string()).collect(),
+    })
+}
+
 /// Drop unnamed remotes (`Ok(None)`) while keeping named ones and errors, for use in a
 /// `filter_map`.
 fn map_remote_names(remote: StdResult<Option<&str>, GitError>) -> Option<StdResult<&str, GitError>> {
@@ -151,6 +237,35 @@ fn map_remote_names(remote: StdResult<Option<&str>, GitError>) -> Option<StdResu
     }
 }
 
+/// Format a Git time in the strict ISO 8601 format, in the time zone it was recorded in (as `git log
+/// --pretty=%aI` does).
+fn format_time(time: Time) -> String {
+    let offset = time.offset_minutes();
+    let local = time.seconds() + i64::from(offset) * 60;
+    let (days, secs) = (local.div_euclid(86_400), local.rem_euclid(86_400));
+
+    // Civil date of a number of days since 1970-01-01, in the proleptic Gregorian calendar.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    let sign = if offset < 0 { '-' } else { '+' };
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}{sign}{:02}:{:02}",
+        secs / 3_600,
+        secs % 3_600 / 60,
+        secs % 60,
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
 /// Turn a borrowed string into an owned one, mapping the empty string to `None`.
 fn map_string(s: &str) -> Option<String> {
     (!s.is_empty()).then(|| String::from(s))
@@ -1,26 +1,257 @@
 //! Backends that read Git metadata from the local repository.
 //!
-//! Two interchangeable implementations of `GitFetcher` expose the same API ([`get_branch`],
-//! [`get_log`] and [`get_remotes`]): one shelling out to the `git` command (default) and one using
-//! [`git2`](https://docs.rs/git2) in-process (the `libgit` feature). Exactly one is compiled in
-//! depending on the feature flag.
+//! Every backend implements the [`GitFetcher`] trait: one shelling out to the `git` command (always
+//! compiled in), one using [`git2`](https://docs.rs/git2) in-process (the `libgit` feature) and one
+//! using the pure Rust [`gix`](https://docs.rs/gix) in-process (the `gitoxide` feature). The
+//! compiled in backends are listed in a registry, in order of preference, and [`open`] picks one
+//! from a [`GitBackend`]: in `auto` mode, a backend failing (an unsupported repository extension,
+//! a missing `git` binary, ...) hands over to the next one.
 //!
-//! [`get_branch`]: cmdgit::GitFetcher::get_branch
-//! [`get_log`]: cmdgit::GitFetcher::get_log
-//! [`get_remotes`]: cmdgit::GitFetcher::get_remotes
+//! The Git diff is not used by the Coveralls payload, so no backend reads it.
 
-mod infos;
-
-#[cfg(not(feature = "libgit"))]
 mod cmdgit;
+mod infos;
 
 #[cfg(feature = "libgit")]
 mod libgit;
 
+#[cfg(feature = "gitoxide")]
+mod gitoxide;
+
+use super::head::SignatureStatus;
+use simple_error::SimpleError;
+use log::{debug, warn};
+use std::{
+    cell::OnceCell,
+    io::{Result, Error},
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
 pub(super) use infos::LogInfos;
 
-#[cfg(not(feature = "libgit"))]
-pub(super) use cmdgit::GitFetcher;
+/// Backend reading the Git metadata from the local repository.
+///
+/// Parses from `auto`, `command`, `libgit`, `gitoxide` or `none`; the in-process backends are only
+/// usable when their cargo feature is enabled.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum GitBackend {
+    /// The first compiled in backend among `libgit`, `gitoxide` and `command` able to read the
+    /// repository, falling back to the next one on error.
+    #[default]
+    Auto,
 
-#[cfg(feature = "libgit")]
-pub(super) use libgit::GitFetcher;
+    /// The `git` command, run as a subprocess.
+    Command,
+
+    /// [`libgit2`](https://libgit2.org), in-process (the `libgit` feature).
+    Libgit,
+
+    /// [`gitoxide`](https://github.com/GitoxideLabs/gitoxide), in-process (the `gitoxide` feature).
+    Gitoxide,
+
+    /// The repository is not read, the Git metadata only comes from the configuration.
+    None,
+}
+
+impl GitBackend {
+    /// The name of the backend, as given on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Auto => "auto",
+            Self::Command => "command",
+            Self::Libgit => "libgit",
+            Self::Gitoxide => "gitoxide",
+            Self::None => "none",
+        }
+    }
+
+    /// Tell whether the backend is compiled in.
+    pub fn is_available(&self) -> bool {
+        match self {
+            Self::Auto | Self::None => true,
+            backend => REGISTRY.iter().any(|(registered, _)| registered == backend),
+        }
+    }
+}
+
+impl FromStr for GitBackend {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Self::Auto),
+            "command" => Ok(Self::Command),
+            "libgit" => Ok(Self::Libgit),
+            "gitoxide" => Ok(Self::Gitoxide),
+            "none" => Ok(Self::None),
+            _ => Err(format!(
+                "unknown Git backend `{s}`, expected `auto`, `command`, `libgit`, `gitoxide` or `none`"
+            )),
+        }
+    }
+}
+
+/// The API every backend reading the local repository implements.
+pub(super) trait GitFetcher {
+    /// Return the current branch name, or `None` in detached `HEAD` state.
+    fn get_branch(&self) -> Result<Option<String>>;
+
+    /// Tell whether the repository is a shallow clone, whose history is truncated.
+    fn is_shallow(&self) -> Result<bool>;
+
+    /// Guess the branch of a detached `HEAD` from the branches containing the commit.
+    fn guess_branch(&self) -> Result<Option<String>>;
+
+    /// Return the information about the `HEAD` commit.
+    fn get_log(&self) -> Result<LogInfos>;
+
+    /// Return the information about the commit `id`, or `None` when the repository lacks it (as in
+    /// a shallow clone).
+    fn get_commit_log(&self, id: &str) -> Result<Option<LogInfos>>;
+
+    /// Return the status of the signature of the `HEAD` commit, `None` when it is not signed.
+    fn get_signature(&self) -> Result<Option<SignatureStatus>>;
+
+    /// Return the list of `(name, url)` remotes, or `None` when there is none.
+    fn get_remotes(&self) -> Result<Option<Vec<(String, String)>>>;
+}
+
+/// Open the repository enclosing a directory with one backend.
+type Opener = fn(&Path) -> Result<Box<dyn GitFetcher>>;
+
+/// The compiled in backends, in order of preference for the `auto` mode.
+const REGISTRY: &[(GitBackend, Opener)] = &[
+    #[cfg(feature = "libgit")]
+    (GitBackend::Libgit, |dir| Ok(Box::new(libgit::LibgitFetcher::new(dir)?))),
+    #[cfg(feature = "gitoxide")]
+    (GitBackend::Gitoxide, |dir| Ok(Box::new(gitoxide::GitoxideFetcher::new(dir)?))),
+    (GitBackend::Command, |dir| Ok(Box::new(cmdgit::CommandFetcher::new(dir)?))),
+];
+
+/// Open the repository enclosing `dir`, be it a regular repository, a linked worktree or a
+/// submodule, with `backend`.
+///
+/// # Errors
+///
+/// Returns an [`std::io::Error`] if the backend is `none` or is not compiled in, or if no backend
+/// can read the repository enclosing `dir`.
+pub(super) fn open(backend: GitBackend, dir: &Path) -> Result<Box<dyn GitFetcher>> {
+    match backend {
+        GitBackend::None => {
+            let msg = "The Git backend is disabled (`--git-backend none`)";
+
+            Err(Error::other(SimpleError::new(msg)))
+        }
+        GitBackend::Auto => Ok(Box::new(Fallback::new(dir)?)),
+        backend => {
+            let Some((_, open)) = REGISTRY.iter().find(|(registered, _)| *registered == backend) else {
+                let msg = format!(
+                    "The `{0}` Git backend is not compiled in, rebuild with the `{0}` feature",
+                    backend.name()
+                );
+
+                return Err(Error::other(SimpleError::new(msg)));
+            };
+
+            debug!("Reading the Git repository with the `{}` backend", backend.name());
+            open(dir)
+        }
+    }
+}
+
+/// A backend of the `auto` mode, `None` once it failed to open the repository.
+type Lazy = OnceCell<Option<Box<dyn GitFetcher>>>;
+
+/// The `auto` mode: every registered backend in turn, each one opened the first time it is needed,
+/// until one succeeds.
+struct Fallback {
+    dir:      PathBuf,
+    backends: Vec<(GitBackend, Opener, Lazy)>,
+}
+
+impl Fallback {
+    /// Prepare the registered backends for the repository enclosing `dir`, checking that one of
+    /// them at least can open it.
+    fn new(dir: &Path) -> Result<Self> {
+        let fallback = Fallback {
+            dir:      dir.to_path_buf(),
+            backends: REGISTRY.iter().map(|(backend, open)| (*backend, *open, OnceCell::new())).collect(),
+        };
+
+        fallback.first(|_| Ok(()))?;
+        Ok(fallback)
+    }
+
+    /// Run `op` on the backends in order of preference, returning the first success or else the
+    /// last error.
+    fn first<T>(&self, op: impl Fn(&dyn GitFetcher) -> Result<T>) -> Result<T> {
+        let mut last_err = None;
+
+        for (backend, open, fetcher) in &self.backends {
+            let mut open_err = None;
+            let fetcher = fetcher.get_or_init(|| match open(&self.dir) {
+                Ok(fetcher) => {
+                    debug!("Reading the Git repository with the `{}` backend", backend.name());
+
+                    Some(fetcher)
+                }
+                Err(err) => {
+                    debug!("The `{}` Git backend cannot open the repository: {err}", backend.name());
+                    open_err.replace(err);
+
+                    None
+                }
+            });
+
+            let Some(fetcher) = fetcher else {
+                last_err = open_err.or(last_err);
+                continue;
+            };
+
+            match op(fetcher.as_ref()) {
+                Ok(v) => return Ok(v),
+                Err(err) => {
+                    warn!("The `{}` Git backend failed, trying the next one: {err}", backend.name());
+                    last_err.replace(err);
+                }
+            }
+        }
+
+        Err(last_err.unwrap_or_else(|| {
+            let msg = "No Git backend can read the repository";
+
+            Error::other(SimpleError::new(msg))
+        }))
+    }
+}
+
+impl GitFetcher for Fallback {
+    fn get_branch(&self) -> Result<Option<String>> {
+        self.first(|fetcher| fetcher.get_branch())
+    }
+
+    fn is_shallow(&self) -> Result<bool> {
+        self.first(|fetcher| fetcher.is_shallow())
+    }
+
+    fn guess_branch(&self) -> Result<Option<String>> {
+        self.first(|fetcher| fetcher.guess_branch())
+    }
+
+    fn get_log(&self) -> Result<LogInfos> {
+        self.first(|fetcher| fetcher.get_log())
+    }
+
+    fn get_commit_log(&self, id: &str) -> Result<Option<LogInfos>> {
+        self.first(|fetcher| fetcher.get_commit_log(id))
+    }
+
+    fn get_signature(&self) -> Result<Option<SignatureStatus>> {
+        self.first(|fetcher| fetcher.get_signature())
+    }
+
+    fn get_remotes(&self) -> Result<Option<Vec<(String, String)>>> {
+        self.first(|fetcher| fetcher.get_remotes())
+    }
+}
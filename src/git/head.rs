@@ -1,34 +1,92 @@
-use super::fetcher::GitFetcher;
+use super::fetcher::{GitBackend, GitFetcher, LogInfos};
 use crate::config::Config;
 use serde::{Serialize, Deserialize};
 use simple_error::SimpleError;
 use const_format::concatcp;
-use log::debug;
-use std::io::{Result, Error, ErrorKind};
+use log::{debug, info, warn};
+use std::{
+    io::{Result, Error, ErrorKind},
+    str::FromStr,
+};
 
 /// Build the error message shown when the `HEAD` information could not be fully collected.
 ///
-/// The hint about a missing `git` binary is only appended when the `libgit` backend is not used.
-const fn error_message() -> &'static str {
+/// The hint about a missing `git` binary is only appended when the `git` command may be used; in
+/// offline mode (no backend), only the variable to set is given.
+fn error_message(backend: GitBackend) -> &'static str {
     const MSG: &str = "\
         Failed collecting git data. \
         Did you use command line arguments ? \
         Are you running coveralls inside a git repository ?\
     ";
 
-    if cfg!(feature = "libgit") {
-        MSG
-    } else {
-        concatcp!(MSG, " Is git installed ?")
+    match backend {
+        GitBackend::None => "The Git commit ID is missing and no repository is read (offline mode): set GIT_ID or --git-id",
+        GitBackend::Command | GitBackend::Auto => concatcp!(MSG, " Is git installed ?"),
+        _ => MSG,
     }
 }
 
+/// Which part of the commit message is collected from the repository.
+///
+/// Parses from `subject` or `full`.
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum MessageFormat {
+    /// The first paragraph of the message, on a single line (as `git log --pretty=%s`).
+    #[default]
+    Subject,
+
+    /// The whole message, subject and body.
+    Full,
+}
+
+impl FromStr for MessageFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "subject" => Ok(Self::Subject),
+            "full" => Ok(Self::Full),
+            _ => Err(format!("unknown message format `{s}`, expected `subject` or `full`")),
+        }
+    }
+}
+
+/// Status of the signature of a commit, as reported by `git log --pretty=%G?`.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SignatureStatus {
+    /// A good, valid signature.
+    Good,
+
+    /// A bad signature.
+    Bad,
+
+    /// A good signature, with a key of unknown validity.
+    UnknownValidity,
+
+    /// A good signature that has expired.
+    Expired,
+
+    /// A good signature, made by an expired key.
+    ExpiredKey,
+
+    /// A good signature, made by a revoked key.
+    RevokedKey,
+
+    /// A signature that cannot be checked (missing key, or a backend unable to verify it).
+    Unverified,
+}
+
 /// Information about the `HEAD` commit of the repository.
 ///
 /// Holds the commit identifier, the author and committer identities and the commit message, as
-/// expected in the `git.head` object of the Coveralls JSON format.
+/// expected in the `git.head` object of the Coveralls JSON format. When read from the local
+/// repository, the dates and the parents of the commit are collected too, and its signature status
+/// for the local reports that ask for it (the `doctor` subcommand): these are not defined by the
+/// Coveralls format, so they are left out of the payload.
 #[derive(Default, Serialize, Deserialize, Clone, Eq, PartialEq)]
-pub(crate) struct GitHead {
+pub struct GitHead {
     #[serde(default)]
     pub(crate) id: String,
 
@@ -46,9 +104,76 @@ pub(crate) struct GitHead {
 
     #[serde(default)]
     pub(crate) message: String,
+
+    #[serde(skip_serializing)]
+    #[serde(default)]
+    pub(crate) author_date: String,
+
+    #[serde(skip_serializing)]
+    #[serde(default)]
+    pub(crate) committer_date: String,
+
+    #[serde(skip_serializing)]
+    #[serde(default)]
+    pub(crate) parents: Vec<String>,
+
+    #[serde(skip_serializing)]
+    #[serde(default)]
+    pub(crate) signature: Option<SignatureStatus>,
 }
 
 impl GitHead {
+    /// The commit identifier (SHA).
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// The author name.
+    pub fn author_name(&self) -> &str {
+        &self.author_name
+    }
+
+    /// The author email.
+    pub fn author_email(&self) -> &str {
+        &self.author_email
+    }
+
+    /// The committer name.
+    pub fn committer_name(&self) -> &str {
+        &self.committer_name
+    }
+
+    /// The committer email.
+    pub fn committer_email(&self) -> &str {
+        &self.committer_email
+    }
+
+    /// The commit message, its subject only unless configured otherwise (see [`MessageFormat`]).
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The author date in the strict ISO 8601 format, empty when unknown.
+    pub fn author_date(&self) -> &str {
+        &self.author_date
+    }
+
+    /// The commit date in the strict ISO 8601 format, empty when unknown.
+    pub fn committer_date(&self) -> &str {
+        &self.committer_date
+    }
+
+    /// The identifiers of the parent commits, empty when unknown (or for a root commit).
+    pub fn parents(&self) -> &[String] {
+        &self.parents
+    }
+
+    /// The status of the commit signature, `None` when the commit is not signed, or when unknown (it
+    /// is only checked for the local reports).
+    pub fn signature(&self) -> Option<SignatureStatus> {
+        self.signature
+    }
+
     /// Overlay the `HEAD` fields coming from the configuration on top of the current values.
     pub(super) fn fetch_from_config(&mut self, config: &Config) {
         if let Some(v) = config.git_id.clone() {
@@ -76,10 +201,46 @@ impl GitHead {
         }
     }
 
-    /// Fill the `HEAD` fields from the last commit reported by the fetcher.
-    pub(super) fn fetch_from_git(&mut self, git_fetcher: &GitFetcher) -> Result<()> {
-        let infos = git_fetcher.get_log()?;
+    /// Fill the `HEAD` fields from the last commit reported by the fetcher, keeping the part of the
+    /// message selected by `format`.
+    pub(super) fn fetch_from_git(&mut self, git_fetcher: &dyn GitFetcher, format: MessageFormat) -> Result<()> {
+        self.fetch_from_log(git_fetcher.get_log()?, format);
+
+        Ok(())
+    }
+
+    /// When the configuration names another commit than the one read from the repository (such as
+    /// the head of a pull request, whose merge commit is checked out), replace the fields read from
+    /// the repository by the ones of that commit, or clear them when the repository lacks it (as in
+    /// a shallow clone), so that they never describe another commit than the reported ID.
+    pub(super) fn fetch_configured_commit(&mut self, git_fetcher: &dyn GitFetcher, config: &Config) -> Result<()> {
+        let Some(id) = config.git_id.as_deref().filter(|id| *id != self.id) else {
+            return Ok(());
+        };
+
+        // Only a commit ID is looked up, never an option or a revision expression.
+        let infos = if !id.is_empty() && id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            git_fetcher.get_commit_log(id)?
+        } else {
+            None
+        };
+
+        *self = GitHead::default();
+
+        match infos {
+            Some(infos) => {
+                debug!("Reading the configured commit `{id}` instead of HEAD");
+
+                self.fetch_from_log(infos, config.param_message_format);
+            }
+            None => info!("The configured commit `{id}` is not in the repository, its metadata are left empty"),
+        }
 
+        Ok(())
+    }
+
+    /// Fill the `HEAD` fields from `infos`, keeping the part of the message selected by `format`.
+    fn fetch_from_log(&mut self, infos: LogInfos, format: MessageFormat) {
         if let Some(id) = infos.id() {
             debug!("Fetched HEAD commit `{id}`");
             self.id = id.to_string();
@@ -101,32 +262,64 @@ impl GitHead {
             self.committer_email = v.to_string();
         }
 
-        if let Some(v) = infos.message() {
-            self.message = v.to_string();
+        if let Some(v) = infos.message(format) {
+            self.message = v;
+        }
+
+        if let Some(v) = infos.author_date() {
+            self.author_date = v.to_string();
+        }
+
+        if let Some(v) = infos.committer_date() {
+            self.committer_date = v.to_string();
         }
 
+        self.parents = infos.parents().to_vec();
+    }
+
+    /// Check the signature of the `HEAD` commit, which may be slow (`gpg` is run by the `git`
+    /// command), so only done for the local reports.
+    pub(super) fn fetch_signature(&mut self, git_fetcher: &dyn GitFetcher) -> Result<()> {
+        self.signature = git_fetcher.get_signature()?;
+
         Ok(())
     }
 
-    /// Ensure every `HEAD` field has been collected.
+    /// Ensure the `HEAD` commit ID has been collected; the other fields are only reported when
+    /// missing, as a truncated history or an unusual commit may lack them.
     ///
     /// # Errors
     ///
-    /// Returns an [`std::io::Error`] when any field is still empty, with a hint about the likely
-    /// cause (not running inside a Git repository, or a missing `git` binary).
-    pub(super) fn check(&self) -> Result<()> {
-        macro_rules! e {
-            ($f:ident) => {
-                self.$f.is_empty()
+    /// Returns an [`std::io::Error`] when the commit ID is empty, with a hint about the likely
+    /// cause (not running inside a Git repository, a missing `git` binary, or a disabled `backend`).
+    pub(super) fn check(&self, backend: GitBackend) -> Result<()> {
+        if self.id.is_empty() {
+            debug!("Collected Git HEAD information has no commit ID");
+
+            return Err(Error::new(ErrorKind::Other, SimpleError::new(error_message(backend))));
+        }
+
+        macro_rules! missing {
+            ($($f:ident => $var:literal),*) => {
+                [$((concatcp!(stringify!($f), " (", $var, ")"), self.$f.is_empty())),*]
+                    .into_iter()
+                    .filter_map(|(name, empty)| empty.then_some(name))
+                    .collect::<Vec<_>>()
             };
         }
 
-        if e!(id) || e!(author_name) || e!(author_email) || e!(committer_name) || e!(committer_email) || e!(message) {
-            debug!("Collected Git HEAD information is incomplete");
+        let missing = missing!(
+            author_name => "GIT_AUTHOR_NAME or --git-author-name",
+            author_email => "GIT_AUTHOR_EMAIL or --git-author-email",
+            committer_name => "GIT_COMMITTER_NAME or --git-committer-name",
+            committer_email => "GIT_COMMITTER_EMAIL or --git-committer-email",
+            message => "GIT_MESSAGE or --git-message"
+        );
 
-            Err(Error::new(ErrorKind::Other, SimpleError::new(error_message())))
-        } else {
-            Ok(())
+        if !missing.is_empty() {
+            warn!("Incomplete Git HEAD information, missing: {}", missing.join(", "));
         }
+
+        Ok(())
     }
 }
@@ -1,8 +1,12 @@
-use super::{fetcher::GitFetcher, remote::GitRemote, head::GitHead};
+use super::{fetcher::{self, GitBackend, GitFetcher}, remote::GitRemote, head::GitHead};
 use crate::config::Config;
-use log::debug;
+use log::{debug, info, warn};
 use serde::{Serialize, Deserialize};
-use std::{io::Result, mem::replace};
+use std::{
+    io::Result,
+    mem::{replace, take},
+    path::Path,
+};
 
 /// Git metadata attached to a coverage report.
 ///
@@ -18,12 +22,32 @@ pub struct GitInfos {
     #[serde(default)]
     pub(crate) branch: String,
 
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub(crate) tag: Option<String>,
+
     #[serde(default)]
     pub(crate) remotes: Vec<GitRemote>,
 }
 
 impl GitInfos {
-    /// Overlay the Git fields coming from the configuration on top of the current values.
+    /// The `HEAD` commit.
+    pub fn head(&self) -> &GitHead {
+        &self.head
+    }
+
+    /// The branch name, empty when unknown.
+    pub fn branch(&self) -> &str {
+        &self.branch
+    }
+
+    /// The tag of the commit, if any.
+    pub fn tag(&self) -> Option<&str> {
+        self.tag.as_deref()
+    }
+
+    /// Overlay the Git fields coming from the configuration on top of the current values, then keep
+    /// the selected remotes only, with sanitized URLs.
     fn fetch_from_config(&mut self, config: &Config) {
         self.head.fetch_from_config(config);
 
@@ -45,36 +69,152 @@ impl GitInfos {
         if let Some(v) = config.git_branch.clone() {
             self.branch = v;
         }
+
+        if let Some(v) = config.git_tag.clone() {
+            self.tag.replace(v);
+        }
+
+        self.remotes = GitRemote::normalize_list(take(&mut self.remotes), config);
     }
 
-    /// Collect the `HEAD` commit, remotes and branch from the local repository.
-    fn fetch_from_git(&mut self) -> Result<()> {
+    /// Collect the `HEAD` commit, remotes and branch from the repository enclosing the
+    /// [`repo_dir`](Config::repo_dir) of the configuration (the current directory by default),
+    /// returning the fetcher for further queries.
+    fn fetch_from_git(&mut self, config: &Config) -> Result<Box<dyn GitFetcher>> {
         debug!("Fetching Git information from the local repository");
 
-        let git_fetcher = GitFetcher::new()?;
+        let dir = config.param_repo_dir.as_deref().unwrap_or(Path::new("."));
+        let git_fetcher = fetcher::open(config.param_git_backend, dir)?;
 
-        self.head.fetch_from_git(&git_fetcher)?;
-        self.remotes = GitRemote::fetch_list(&git_fetcher, replace(&mut self.remotes, vec![]))?;
+        if git_fetcher.is_shallow().unwrap_or_default() {
+            info!("The repository is a shallow clone, its history is truncated");
+        }
 
-        if let Some(v) = git_fetcher.get_branch()? {
-            debug!("Resolved Git branch `{v}`");
+        self.head.fetch_from_git(git_fetcher.as_ref(), config.param_message_format)?;
+        self.remotes = GitRemote::fetch_list(git_fetcher.as_ref(), replace(&mut self.remotes, vec![]))?;
 
-            self.branch = v;
+        // A branch that cannot be resolved is handled as a detached `HEAD`: it comes from the
+        // configuration or is guessed.
+        match git_fetcher.get_branch() {
+            Ok(Some(v)) => {
+                debug!("Resolved Git branch `{v}`");
+
+                self.branch = v;
+            }
+            Ok(None) => debug!("The HEAD of the repository is detached"),
+            Err(err) => warn!("The branch of HEAD cannot be resolved, handling HEAD as detached: {err}"),
+        }
+
+        Ok(git_fetcher)
+    }
+
+    /// When the branch is still unknown (detached `HEAD` and no branch in the configuration), guess
+    /// it from the branches of the repository.
+    fn guess_branch(&mut self, git_fetcher: &dyn GitFetcher) {
+        if !self.branch.is_empty() {
+            return;
+        }
+
+        match git_fetcher.guess_branch() {
+            Ok(Some(v)) => {
+                info!("HEAD is detached, using the branch `{v}` containing it");
+
+                self.branch = v;
+            }
+            Ok(None) => warn!("HEAD is detached and no branch contains it: set the branch with GIT_BRANCH or --git-branch"),
+            Err(err) => warn!("HEAD is detached and its branch cannot be guessed: {err}"),
+        }
+    }
+
+    /// Collect the Git metadata of the repository alone, without the configuration overlay nor the
+    /// completeness check, for the local reports: the signature of the commit is checked too. Only
+    /// the parameters of `config` (repository directory, message format) are used.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the repository cannot be read.
+    pub(crate) fn from_repository(config: &Config) -> Result<GitInfos> {
+        let mut infos = GitInfos::default();
+        let git_fetcher = infos.fetch_from_git(config)?;
+
+        if let Err(err) = infos.head.fetch_signature(git_fetcher.as_ref()) {
+            warn!("The signature of the HEAD commit cannot be checked: {err}");
         }
 
-        Ok(())
+        infos.guess_branch(git_fetcher.as_ref());
+        infos.remotes = GitRemote::normalize_list(take(&mut infos.remotes), config);
+
+        Ok(infos)
+    }
+
+    /// Build the Git metadata from the configuration alone, without any access to a repository (the
+    /// offline mode, for builds from a source tarball for instance).
+    ///
+    /// Only the commit ID is required, and only once some Git value is configured: with none at
+    /// all, the job is sent without Git metadata (and a warning). The missing optional values are
+    /// reported along with the variables to set.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if Git values are configured but not the commit ID.
+    pub(crate) fn from_config(config: &Config) -> Result<Option<GitInfos>> {
+        let mut infos = GitInfos::default();
+
+        infos.fetch_from_config(config);
+
+        if infos == GitInfos::default() {
+            warn!("No Git metadata is configured, the job is not linked to a commit: set GIT_ID and GIT_BRANCH (or --git-id and --git-branch)");
+
+            return Ok(None);
+        }
+
+        infos.check_branch();
+        infos.head.check(GitBackend::None)?;
+
+        Ok(Some(infos))
+    }
+
+    /// Warn when the branch is unknown, naming the variables setting it.
+    fn check_branch(&self) {
+        if self.branch.is_empty() {
+            warn!("The Git branch is unknown: set GIT_BRANCH or --git-branch");
+        }
     }
 
     /// Resolve the Git metadata: read the local repository, then overlay the configuration.
     ///
+    /// The repository is the one enclosing the [`repo_dir`](Config::repo_dir) of the configuration, or
+    /// the current directory; it can be a linked worktree or a submodule.
+    ///
+    /// In detached `HEAD` state (as in most CI checkouts), the branch comes from the configuration
+    /// or, failing that, is guessed from the branches containing the commit; in a shallow clone, the
+    /// parents of the commit may be missing. With the `none` Git backend, the configuration alone is
+    /// used.
+    ///
     /// # Errors
     ///
-    /// Returns an [`std::io::Error`] if the repository cannot be read or if the resulting `HEAD`
-    /// information is incomplete (see [`GitHead::check`]).
+    /// Returns an [`std::io::Error`] if the repository cannot be read, or if the resulting `HEAD`
+    /// information lacks the commit ID (see [`GitHead::check`]).
     pub fn update(&mut self, config: &Config) -> Result<()> {
-        self.fetch_from_git()?;
+        let git_fetcher = if config.is_offline() {
+            debug!("The Git backend is disabled, only the configuration is used");
+
+            None
+        } else {
+            Some(self.fetch_from_git(config)?)
+        };
+
+        if let Some(git_fetcher) = &git_fetcher {
+            self.head.fetch_configured_commit(git_fetcher.as_ref(), config)?;
+        }
+
         self.fetch_from_config(config);
 
-        self.head.check()
+        match git_fetcher {
+            Some(git_fetcher) => self.guess_branch(git_fetcher.as_ref()),
+            None => self.check_branch(),
+        }
+
+        self.head.check(config.param_git_backend)
     }
 }
@@ -2,11 +2,17 @@
 //!
 //! [`GitInfos`] is the serializable structure embedded in a [`Coverage`](crate::Coverage) report.
 //! It is filled from the [`Config`](crate::config::Config) and, when needed, from the local
-//! repository through the [`fetcher`] backend (either the `git` command or `libgit2`).
+//! repository through the [`fetcher`] backend (the `git` command, `libgit2` or `gitoxide`).
 
 mod fetcher;
 mod head;
 mod infos;
 mod remote;
 
-pub use infos::GitInfos;
+pub use self::{
+    fetcher::GitBackend,
+    head::{GitHead, MessageFormat, SignatureStatus},
+    infos::GitInfos,
+};
+
+pub(crate) use self::remote::sanitize_url;
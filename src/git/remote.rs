@@ -1,9 +1,108 @@
 use super::fetcher::GitFetcher;
 use crate::config::Config;
-use log::debug;
+use log::{debug, warn};
 use serde::{Serialize, Deserialize};
 use std::{io::Result, collections::HashMap};
 
+/// URL schemes reaching a repository over SSH or the Git protocol, turned into HTTPS URLs by the
+/// canonical form.
+const SSH_SCHEMES: &[&str] = &["ssh", "git+ssh", "ssh+git", "git"];
+
+/// A remote URL split into its parts: `scheme://userinfo@host/path`, or `userinfo@host:path` for
+/// the scp-like syntax (which has no scheme). Local paths are not parsed.
+struct RemoteUrl<'a> {
+    scheme:   Option<&'a str>,
+    userinfo: Option<&'a str>,
+    host:     &'a str,
+    path:     &'a str,
+}
+
+impl<'a> RemoteUrl<'a> {
+    /// Split `url`, `None` for a local path.
+    fn parse(url: &'a str) -> Option<Self> {
+        let (scheme, rest, separator) = match url.split_once("://") {
+            Some((scheme, rest)) => (Some(scheme), rest, '/'),
+            None => (None, url, ':'),
+        };
+
+        let (authority, path) = match rest.split_once(separator) {
+            Some((authority, path)) => (authority, path),
+            None if scheme.is_some() => (rest, ""),
+            None => return None,
+        };
+
+        // Neither a local path with a colon nor a Windows drive letter are scp-like URLs.
+        if scheme.is_none() && (authority.contains('/') || authority.len() < 2) {
+            return None;
+        }
+
+        let (userinfo, host) = match authority.rsplit_once('@') {
+            Some((userinfo, host)) => (Some(userinfo), host),
+            None => (None, authority),
+        };
+
+        Some(Self {
+            scheme,
+            userinfo,
+            host,
+            path,
+        })
+    }
+
+    /// Tell whether the URL reaches the repository over SSH (including the scp-like syntax) or the
+    /// Git protocol.
+    fn is_ssh(&self) -> bool {
+        self.scheme
+            .is_none_or(|scheme| SSH_SCHEMES.contains(&scheme.to_ascii_lowercase().as_str()))
+    }
+
+    /// The host without its port, if any.
+    fn hostname(&self) -> &'a str {
+        match self.host.rsplit_once(':') {
+            Some((hostname, port)) if port.bytes().all(|b| b.is_ascii_digit()) => hostname,
+            _ => self.host,
+        }
+    }
+}
+
+/// Remove the credentials from a remote URL: the whole user information of an HTTP(S) URL (where
+/// tokens are passed as user name or password), and the password of an SSH URL (whose user name,
+/// like `git`, is no secret). With `canonical`, SSH, scp-like and `git://` URLs are turned into
+/// `https://host/path` URLs.
+///
+/// Return the sanitized URL, and whether credentials were removed.
+pub(crate) fn sanitize_url(url: &str, canonical: bool) -> (String, bool) {
+    let Some(parts) = RemoteUrl::parse(url) else {
+        return (url.to_string(), false);
+    };
+
+    let user = parts.userinfo.filter(|_| parts.is_ssh()).map(|userinfo| match userinfo.split_once(':') {
+        Some((user, _)) => user,
+        None => userinfo,
+    });
+
+    let had_credentials = parts.userinfo.is_some_and(|userinfo| Some(userinfo) != user);
+    let canonical = canonical && parts.is_ssh();
+
+    if !had_credentials && !canonical {
+        return (url.to_string(), false);
+    }
+
+    let url = match (canonical, parts.scheme) {
+        (true, _) => format!("https://{}/{}", parts.hostname(), parts.path.trim_start_matches('/')),
+        (false, Some(scheme)) => match user {
+            Some(user) => format!("{scheme}://{user}@{}/{}", parts.host, parts.path),
+            None => format!("{scheme}://{}/{}", parts.host, parts.path),
+        },
+        (false, None) => match user {
+            Some(user) => format!("{user}@{}:{}", parts.host, parts.path),
+            None => format!("{}:{}", parts.host, parts.path),
+        },
+    };
+
+    (url, had_credentials)
+}
+
 /// Map of remote name to remote URL, used to merge the configured and fetched remotes.
 type Dict = HashMap<String, String>;
 
@@ -36,7 +135,7 @@ impl GitRemote {
     ///
     /// Remotes returned by the fetcher take precedence; any `old` remote whose name was not seen is
     /// appended so that no previously known remote is lost.
-    pub(super) fn fetch_list(git_fetcher: &GitFetcher, old: Vec<GitRemote>) -> Result<Vec<GitRemote>> {
+    pub(super) fn fetch_list(git_fetcher: &dyn GitFetcher, old: Vec<GitRemote>) -> Result<Vec<GitRemote>> {
         let mut dict = old.into_iter().map(|entry| (entry.name, entry.url)).collect::<Dict>();
         let mut res = match git_fetcher.get_remotes()? {
             None => vec![],
@@ -53,6 +152,35 @@ impl GitRemote {
         Ok(res)
     }
 
+    /// Keep the remotes selected by the configuration (every remote when no name is configured), and
+    /// sanitize their URLs (see [`sanitize_url`]).
+    pub(super) fn normalize_list(remotes: Vec<GitRemote>, config: &Config) -> Vec<GitRemote> {
+        let keep = &config.param_keep_remotes;
+        let total = remotes.len();
+        let res = remotes
+            .into_iter()
+            .filter(|remote| keep.is_empty() || keep.contains(&remote.name))
+            .map(|remote| {
+                let (url, had_credentials) = sanitize_url(&remote.url, config.param_canonical_remotes);
+
+                if had_credentials {
+                    warn!("Credentials removed from the URL of the Git remote `{}`", remote.name);
+                }
+
+                GitRemote {
+                    url,
+                    ..remote
+                }
+            })
+            .collect::<Vec<_>>();
+
+        if res.len() < total {
+            debug!("Kept {} of {total} Git remote(s)", res.len());
+        }
+
+        res
+    }
+
     /// Build a remote from the configuration when both its name and URL are set.
     pub(super) fn fetch_from_config(config: &Config) -> Option<Self> {
         if let Some(name) = config.git_remote_name.clone() {
@@ -2,7 +2,7 @@
 
 use itertools::Itertools;
 use std::{
-    path::{Component, PathBuf},
+    path::{Component, Path, PathBuf},
     borrow::Cow,
 };
 
@@ -28,6 +28,30 @@ pub(super) fn path_to_string(path: &PathBuf) -> String {
     path.components().filter_map(path_component_as_str).join("/")
 }
 
+/// Normalize a path lexically: `.` components are dropped and `..` components cancel the preceding
+/// directory, without looking at the file system.
+///
+/// This lets `./src/lib.rs`, `src/lib.rs` and `src/bin/../lib.rs` be recognized as the same file.
+pub(super) fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(_)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir | Component::Prefix(_)) => {}
+                _ => normalized.push(".."),
+            },
+            other => normalized.push(other.as_os_str()),
+        }
+    }
+
+    normalized
+}
+
 /// Mask a secret for logging, keeping only the last few characters visible.
 ///
 /// A secret long enough to spare a hint is rendered as `****` followed by its last four characters;
@@ -45,3 +69,12 @@ pub(super) fn mask_secret(secret: &str) -> String {
         String::from("****")
     }
 }
+
+/// Coverage percentage of `covered` items over `valid` ones; an empty set counts as fully covered.
+pub(super) fn percent(covered: usize, valid: usize) -> f64 {
+    if valid == 0 {
+        100.0
+    } else {
+        covered as f64 * 100.0 / valid as f64
+    }
+}
@@ -32,6 +32,19 @@
 //!
 //! # Read the report from a file and guess the service from the environment.
 //! coveralls coverage.json env
+//!
+//! # Compare the report with the payload of a previous run, failing on regressions.
+//! coveralls coverage.json compare --baseline main.json --fail-on-regression
+//!
+//! # Record the CI environment, then replay it elsewhere as a dry run.
+//! coveralls --output ci-env.json snapshot
+//! coveralls --replay ci-env.json coverage.json env
+//!
+//! # Check a report against the Coveralls job format.
+//! coveralls coverage.json validate
+//!
+//! # Explain where every configuration value comes from.
+//! coveralls coverage.json doctor
 //! ```
 //!
 //! Run `coveralls --help`, or `coveralls <service> --help`, for the list of accepted command line
@@ -55,11 +68,16 @@
 //!
 //! Git metadata about the `HEAD` commit (author, committer, message, branch, remotes) is collected
 //! from the local repository when it is missing from the report or when it is explicitly requested
-//! with `--force-fetch-git-infos`. Two backends are available:
+//! with `--force-fetch-git-infos`. Three backends are available:
 //!
 //! - **default**: the `git` executable is invoked as a subprocess, so `git` must be available in the `PATH`.
 //! - **`libgit`**: the repository is read in-process through [`git2`](https://docs.rs/git2), which removes the
 //!   dependency on an external `git` binary.
+//! - **`gitoxide`**: the repository is read in-process through the pure Rust [`gix`](https://docs.rs/gix), which
+//!   needs neither `libgit2` nor a `git` binary.
+//!
+//! When several are compiled in, they are tried in turn and the next one takes over when one fails;
+//! [`GitBackend`] (or `--git-backend`) forces one of them, or disables the reading of the repository.
 //!
 //! # Library usage
 //!
@@ -75,23 +93,58 @@
 //! }
 //! ```
 //!
+//! [`work_with_logger`] also hands the [`LogFormat`] selected with `--log-format` to a logger
+//! initialization, which is how the binary sets up its logs.
+//!
+//! To embed the whole workflow in another tool, or to test it, [`run_with`] takes the command line
+//! arguments and the environment explicitly instead of reading them from the process:
+//!
+//! ```rust,no_run
+//! use std::collections::HashMap;
+//!
+//! let env = HashMap::from([(String::from("COVERALLS_REPO_TOKEN"), String::from("my-token"))]);
+//! let args = ["coveralls", "--no-send", "coverage.json", "circleci"];
+//!
+//! coveralls::run_with(args, env).expect("the workflow should succeed");
+//! ```
+//!
 //! For finer grained control, the individual stages are available as well: build a [`Config`] from
-//! the command line or the environment, parse a [`Coverage`] report with [`Coverage::from_reader`],
-//! then let a [`CoverallsManager`] enrich and upload it. [`Env`] and [`Service`] are the supporting
-//! types used to read environment variables and identify the CI service.
+//! the environment or with [`Config::builder`], parse a [`Coverage`] report with
+//! [`Coverage::from_reader`] (or assemble one with [`Coverage::new`] and [`SourceFile::new`]), then
+//! let a [`CoverallsManager`] enrich and upload it. [`Env`] and [`Service`] are the supporting
+//! types used to read environment variables and identify the CI service. A report can be checked
+//! with [`Validation`] and compared with a baseline one through [`Comparison`], and the relevant
+//! part of a CI environment can be recorded with [`EnvSnapshot`]. The secrets are scrubbed from the
+//! logs by a [`RedactingLogger`], with the [`Redactor`] of the run in progress.
 #![warn(missing_docs)]
 
 mod api;
 mod cli_args;
+mod compare;
 mod config;
 mod coverage;
 mod coveralls;
 mod env;
+mod export;
 mod git;
 mod helpers;
+mod redact;
 mod service;
+mod spool;
+mod validate;
 mod work;
 
-pub use self::{env::Env, coverage::Coverage, coveralls::CoverallsManager, config::Config, service::Service};
+pub use self::{
+    env::{DotEnv, Env, EnvSnapshot, EnvSource, LayeredEnv, ProcessEnv},
+    coverage::{Branch, Coverage, MergeMode, SourceFile},
+    coveralls::CoverallsManager,
+    config::{Config, ConfigBuilder},
+    git::{GitBackend, GitHead, GitInfos, MessageFormat, SignatureStatus},
+    service::Service,
+    compare::{Comparison, FileComparison, FileStatus},
+    validate::{Issue, Validation},
+    redact::{RedactingLogger, Redactor},
+    cli_args::LogFormat,
+};
 
-pub use work::work;
+pub use work::{work, work_with_logger, run_with};
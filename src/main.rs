@@ -1,17 +1,79 @@
 //! Binary entry point of the `coveralls` command line tool.
 //!
-//! It initializes the logger and delegates the whole work to [`coveralls::work`], exiting with a
-//! non-zero status code when it fails.
+//! It delegates the whole work to [`coveralls::work_with_logger`], initializing the logger from the
+//! parsed arguments, and exits with a non-zero status code when it fails. With `--log-format json`,
+//! every log record is written as a single JSON object, suitable for log pipelines. The records go
+//! through a [`RedactingLogger`], so that no known secret is written to the logs.
 
-use coveralls::work;
-use log::error;
-use std::process::exit;
+use coveralls::{work_with_logger, LogFormat, RedactingLogger};
+use env_logger::{Builder, fmt::Formatter};
+use log::{
+    error,
+    kv::{Key, Value, VisitSource},
+    Record,
+};
 
-/// Initialize logging and run the [`coveralls::work`] workflow, exiting with `1` on error.
-fn main() {
-    env_logger::init();
+use serde_json::{Map, Value as Json};
+use std::{io::Write, process::exit};
+
+/// Collector of the structured fields of a log record into a JSON object.
+struct Fields<'a>(&'a mut Map<String, Json>);
+
+impl<'kvs> VisitSource<'kvs> for Fields<'_> {
+    fn visit_pair(&mut self, key: Key<'kvs>, value: Value<'kvs>) -> Result<(), log::kv::Error> {
+        let value = if let Some(v) = value.to_bool() {
+            Json::from(v)
+        } else if let Some(v) = value.to_u64() {
+            Json::from(v)
+        } else if let Some(v) = value.to_i64() {
+            Json::from(v)
+        } else if let Some(v) = value.to_f64() {
+            Json::from(v)
+        } else {
+            Json::from(value.to_string())
+        };
+
+        self.0.insert(key.to_string(), value);
+
+        Ok(())
+    }
+}
+
+/// Write `record` as a single-line JSON object.
+fn format_json(buf: &mut Formatter, record: &Record) -> std::io::Result<()> {
+    let mut object = Map::new();
+
+    object.insert(String::from("timestamp"), Json::from(buf.timestamp().to_string()));
+    object.insert(String::from("level"), Json::from(record.level().as_str()));
+    object.insert(String::from("target"), Json::from(record.target()));
+    object.insert(String::from("message"), Json::from(record.args().to_string()));
+
+    if let Err(err) = record.key_values().visit(&mut Fields(&mut object)) {
+        object.insert(String::from("fields_error"), Json::from(err.to_string()));
+    }
 
-    if let Err(err) = work() {
+    writeln!(buf, "{}", Json::Object(object))
+}
+
+/// Initialize the logger, writing the records in `format`.
+fn init_logger(format: LogFormat) {
+    let mut builder = Builder::from_default_env();
+
+    if format == LogFormat::Json {
+        builder.format(format_json);
+    }
+
+    let logger = builder.build();
+    let level = logger.filter();
+
+    if log::set_boxed_logger(Box::new(RedactingLogger::new(logger))).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+/// Run the [`coveralls::work_with_logger`] workflow, exiting with `1` on error.
+fn main() {
+    if let Err(err) = work_with_logger(init_logger) {
         error!("{err}");
         exit(1);
     }
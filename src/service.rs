@@ -22,6 +22,13 @@ pub enum Service {
     /// GitHub Actions (`github-actions`).
     GithubActions,
 
+    /// GitHub Actions authenticated with the `GITHUB_TOKEN` of the workflow instead of a Coveralls
+    /// repository token (`github`).
+    ///
+    /// It reads the same variables as [`Service::GithubActions`], but the job ID is the workflow run
+    /// ID, as Coveralls expects for this service name.
+    Github,
+
     /// Jenkins (`jenkins`).
     Jenkins,
 
@@ -33,10 +40,22 @@ pub enum Service {
 }
 
 impl Service {
+    /// Every supported service.
+    pub const ALL: [Service; 8] = [
+        Service::AppVeyor,
+        Service::BuildKite,
+        Service::CircleCI,
+        Service::GithubActions,
+        Service::Github,
+        Service::Jenkins,
+        Service::Semaphore,
+        Service::Travis,
+    ];
+
     /// Return the service matching its Coveralls service name, if any.
     ///
     /// The recognized names are `circleci`, `travis-ci`, `appveyor`, `jenkins`, `semaphore-ci`,
-    /// `github-actions` and `buildkite`. Any other name yields `None`.
+    /// `github-actions`, `github` and `buildkite`. Any other name yields `None`.
     pub fn from_name(name: &str) -> Option<Service> {
         match name {
             "circleci" => Some(Service::CircleCI),
@@ -45,6 +64,7 @@ impl Service {
             "jenkins" => Some(Service::Jenkins),
             "semaphore-ci" => Some(Service::Semaphore),
             "github-actions" => Some(Service::GithubActions),
+            "github" => Some(Service::Github),
             "buildkite" => Some(Service::BuildKite),
             _ => {
                 trace!("Service name `{name}` is not recognized");
@@ -120,6 +140,7 @@ impl Service {
             Self::AppVeyor => "appveyor",
             Self::BuildKite => "buildkite",
             Self::GithubActions => "github-actions",
+            Self::Github => "github",
             Self::Jenkins => "jenkins",
             Self::Semaphore => "semaphore-ci",
             Self::Travis => "travis-ci",
@@ -0,0 +1,214 @@
+//! Temporary storage of the source files of a report on disk.
+//!
+//! The command line workflow reads a report into a [`SpooledCoverage`]: the fields of the job stay in
+//! memory, while every kept source file is written to a temporary [`SourceSpool`] as soon as it is
+//! read. The later stages (merging, branch normalization, validation, writing and uploading the
+//! payload) then load one source file at a time, so that their memory use is proportional to the
+//! largest file entry, plus a small index of the file names and of their place in the spool.
+
+use crate::{
+    coverage::{Coverage, SourceFile},
+    validate::Validation,
+};
+
+use simple_error::SimpleError;
+use log::{debug, warn};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Error, Read, Result, Seek, SeekFrom, Write},
+    path::PathBuf,
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Number of spools created by the process, which tells their files apart.
+static SPOOLS: AtomicUsize = AtomicUsize::new(0);
+
+/// The place of a source file entry in the spool, as `(offset, length)`.
+type Entry = (u64, usize);
+
+/// Source file entries serialized as JSON into a temporary file, removed when dropped.
+///
+/// The entries are grouped by file name, in the order the names first appear, so that the entries of
+/// a same file can be merged.
+pub(crate) struct SourceSpool {
+    path:      PathBuf,
+    writer:    BufWriter<File>,
+    reader:    File,
+    written:   u64,
+    files:     Vec<Vec<Entry>>,
+    positions: HashMap<String, usize>,
+}
+
+impl SourceSpool {
+    /// Create an empty spool in the temporary directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the temporary file cannot be created.
+    pub(crate) fn new() -> Result<Self> {
+        let seq = SPOOLS.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("coveralls-{}-{seq}.spool", process::id()));
+        let writer = OpenOptions::new().write(true).create_new(true).open(&path)?;
+
+        debug!("Spooling the source files to `{}`", path.display());
+
+        Ok(Self {
+            reader: File::open(&path)?,
+            writer: BufWriter::new(writer),
+            path,
+            written: 0,
+            files: vec![],
+            positions: HashMap::new(),
+        })
+    }
+
+    /// Append `source` to the spool.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the entry cannot be serialized or written.
+    pub(crate) fn push(&mut self, source: &SourceFile) -> Result<()> {
+        let json = serde_json::to_vec(source)?;
+        let entry = (self.written, json.len());
+
+        self.writer.write_all(&json)?;
+        self.written += json.len() as u64;
+
+        match self.positions.get(&source.name) {
+            Some(&idx) => self.files[idx].push(entry),
+            None => {
+                self.positions.insert(source.name.clone(), self.files.len());
+                self.files.push(vec![entry]);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Number of distinct source files.
+    pub(crate) fn files(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Number of entries, counting the entries of a same file.
+    pub(crate) fn entries(&self) -> usize {
+        self.files.iter().map(Vec::len).sum()
+    }
+
+    /// Read every entry of the file at `idx`, in the order they were written.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the spool cannot be read back.
+    pub(crate) fn read_file(&mut self, idx: usize) -> Result<Vec<SourceFile>> {
+        self.writer.flush()?;
+
+        let reader = &mut self.reader;
+
+        self.files[idx].iter().map(|entry| read_entry(reader, *entry)).collect()
+    }
+
+    /// Iterate over the entries, file after file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the pending entries cannot be written; the iterator yields
+    /// an error for every entry that cannot be read back.
+    pub(crate) fn iter(&mut self) -> Result<impl Iterator<Item = Result<SourceFile>> + '_> {
+        self.writer.flush()?;
+
+        let reader = &mut self.reader;
+
+        Ok(self.files.iter().flatten().map(move |entry| read_entry(reader, *entry)))
+    }
+}
+
+impl Drop for SourceSpool {
+    fn drop(&mut self) {
+        if let Err(err) = fs::remove_file(&self.path) {
+            warn!("Cannot remove the temporary file `{}`: {err}", self.path.display());
+        }
+    }
+}
+
+/// Read back the source file entry at `entry` of a spool.
+fn read_entry(reader: &mut File, (offset, len): Entry) -> Result<SourceFile> {
+    let mut json = vec![0; len];
+
+    reader.seek(SeekFrom::Start(offset))?;
+    reader.read_exact(&mut json)?;
+
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// A coverage report whose source files are held in a [`SourceSpool`] rather than in memory.
+pub(crate) struct SpooledCoverage {
+    /// The fields of the job, without any source file.
+    pub(crate) coverage: Coverage,
+
+    /// The source files.
+    pub(crate) sources: SourceSpool,
+}
+
+impl SpooledCoverage {
+    /// Validate the report, one source file at a time (see [`Validation`]).
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the spool cannot be read back or an entry cannot be
+    /// serialized to JSON.
+    pub(crate) fn validate(&mut self) -> Result<Validation> {
+        Validation::of_sources(self.sources.iter()?)
+    }
+
+    /// Load the source files into a report of their own, for the stages that need them all at once.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the spool cannot be read back.
+    pub(crate) fn load_sources(&mut self) -> Result<Coverage> {
+        let mut coverage = Coverage::new();
+
+        coverage.source_files = self.sources.iter()?.collect::<Result<_>>()?;
+
+        Ok(coverage)
+    }
+
+    /// Serialize the report to `writer` as JSON, the source files being read back one at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the spool cannot be read back, or if the report cannot be
+    /// serialized or written.
+    pub(crate) fn write_json<W: Write>(&mut self, mut writer: W) -> Result<()> {
+        let Value::Object(mut fields) = serde_json::to_value(&self.coverage)? else {
+            return Err(Error::other(SimpleError::new("The job is not serialized as a JSON object")));
+        };
+
+        fields.remove("source_files");
+        writer.write_all(b"{")?;
+
+        for (key, value) in &fields {
+            serde_json::to_writer(&mut writer, key)?;
+            writer.write_all(b":")?;
+            serde_json::to_writer(&mut writer, value)?;
+            writer.write_all(b",")?;
+        }
+
+        writer.write_all(br#""source_files":["#)?;
+
+        for (idx, source) in self.sources.iter()?.enumerate() {
+            if idx > 0 {
+                writer.write_all(b",")?;
+            }
+
+            serde_json::to_writer(&mut writer, &source?)?;
+        }
+
+        writer.write_all(b"]}")?;
+        writer.flush()
+    }
+}
@@ -0,0 +1,376 @@
+//! Structural and semantic validation of a coverage report against the Coveralls job format.
+//!
+//! The checks run on the JSON representation of the report, so that a file can be validated before
+//! it is even parsed as a [`Coverage`] (a negative hit count, for instance, makes the parsing fail
+//! with an opaque message). A parsed report is validated the same way, each source file being
+//! serialized back on its own, and the source files that cannot be parsed are diagnosed by the same
+//! checks while the report is read.
+
+use crate::coverage::{Coverage, SourceFile};
+use log::debug;
+use serde_json::{Map, Value};
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    fmt,
+    io::{Read, Result},
+};
+
+/// A problem found in a coverage report.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Issue {
+    location: String,
+    message:  String,
+}
+
+impl Issue {
+    /// Build an issue about the report as a whole.
+    fn report(message: impl Into<String>) -> Self {
+        Self {
+            location: String::from("report"),
+            message:  message.into(),
+        }
+    }
+
+    /// Build an issue about the source file at `idx`, named `name` when it has a usable name.
+    fn file(idx: usize, name: Option<&str>, message: impl Into<String>) -> Self {
+        let location = match name {
+            Some(name) => format!("source_files[{idx}] `{name}`"),
+            None => format!("source_files[{idx}]"),
+        };
+
+        Self {
+            location,
+            message: message.into(),
+        }
+    }
+
+    /// Where the issue is: `report`, or the index and name of the source file.
+    pub fn location(&self) -> &str {
+        &self.location
+    }
+
+    /// What is wrong.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.location, self.message)
+    }
+}
+
+/// Describe the type of a JSON value, for the diagnostics.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+/// Check that `value` is a hit count, returning a description of the problem otherwise.
+fn check_count(value: &Value) -> Option<String> {
+    match value {
+        Value::Number(n) if n.is_u64() => None,
+        Value::Number(n) if n.is_i64() => Some(format!("negative count {n}")),
+        Value::Number(n) => Some(format!("count {n} is not an integer")),
+        other => Some(format!("expected a count, found {}", kind(other))),
+    }
+}
+
+/// Result of the validation of a coverage report.
+///
+/// ```rust
+/// use coveralls::Validation;
+///
+/// let json =
+///     r#"{"source_files":[{"name":"src/lib.rs","source_digest":"d41d8cd9","coverage":[1,-2]}]}"#;
+/// let validation = Validation::from_reader(json.as_bytes()).unwrap();
+///
+/// assert!(!validation.is_valid());
+/// assert_eq!(
+///     validation.issues()[0].to_string(),
+///     "source_files[0] `src/lib.rs`: line 2: negative count -2"
+/// );
+/// ```
+#[derive(Debug, Default)]
+pub struct Validation {
+    files:  usize,
+    issues: Vec<Issue>,
+}
+
+impl Validation {
+    /// Validate a parsed coverage report.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if the report cannot be serialized to JSON.
+    pub fn new(coverage: &Coverage) -> Result<Self> {
+        Self::of_sources(coverage.source_files.iter().map(Ok))
+    }
+
+    /// Validate the source files of a parsed report, given one at a time.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error of `sources`, or an [`std::io::Error`] if a source file cannot be
+    /// serialized to JSON.
+    pub(crate) fn of_sources<S: Borrow<SourceFile>>(sources: impl Iterator<Item = Result<S>>) -> Result<Self> {
+        let mut validation = Self::default();
+        let mut names = HashMap::new();
+
+        for (idx, source) in sources.enumerate() {
+            validation.check_file(idx, &serde_json::to_value(source?.borrow())?, &mut names);
+            validation.files += 1;
+        }
+
+        validation.log();
+
+        Ok(validation)
+    }
+
+    /// Validate a coverage report in the Coveralls JSON format, read from `rdr`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`std::io::Error`] if reading fails or if the data is not JSON at all; every
+    /// other problem is reported as an [`Issue`].
+    pub fn from_reader<R: Read>(rdr: R) -> Result<Self> {
+        Ok(Self::of_value(&serde_json::from_reader(rdr)?))
+    }
+
+    /// Validate the JSON representation of a report.
+    fn of_value(report: &Value) -> Self {
+        let mut validation = Self::default();
+
+        let Some(report) = report.as_object() else {
+            validation
+                .issues
+                .push(Issue::report(format!("expected an object, found {}", kind(report))));
+
+            return validation;
+        };
+
+        match report.get("source_files") {
+            None => validation.issues.push(Issue::report("missing `source_files`")),
+            Some(Value::Array(files)) => {
+                let mut names = HashMap::new();
+
+                for (idx, file) in files.iter().enumerate() {
+                    validation.check_file(idx, file, &mut names);
+                }
+
+                validation.files = files.len();
+            }
+            Some(other) => validation.issues.push(Issue::report(format!(
+                "`source_files` must be an array, found {}",
+                kind(other)
+            ))),
+        }
+
+        validation.log();
+
+        validation
+    }
+
+    /// Record the issues of the source file at `idx`, given as JSON, that could not be parsed: those
+    /// found by the checks, or else the parsing error `err`. Duplicates are not looked for, as the
+    /// entries of a same file are merged once read.
+    pub(crate) fn add_unparsed_file(&mut self, idx: usize, file: &Value, err: &dyn fmt::Display) {
+        let issues = self.issues.len();
+
+        self.check_file(idx, file, &mut HashMap::new());
+
+        if self.issues.len() == issues {
+            let name = file.get("name").and_then(Value::as_str).filter(|name| !name.is_empty());
+
+            self.issues.push(Issue::file(idx, name, err.to_string()));
+        }
+    }
+
+    /// Set the number of source files of the report, once it is read.
+    pub(crate) fn set_files(&mut self, files: usize) {
+        self.files = files;
+    }
+
+    /// Log the outcome of the validation.
+    fn log(&self) {
+        debug!(
+            "Validated {} source file(s), {} issue(s) found",
+            self.files,
+            self.issues.len()
+        );
+    }
+
+    /// Validate the source file at `idx`; `names` maps the names seen so far to their index.
+    fn check_file(&mut self, idx: usize, file: &Value, names: &mut HashMap<String, usize>) {
+        let Some(file) = file.as_object() else {
+            self.issues.push(Issue::file(
+                idx,
+                None,
+                format!("expected an object, found {}", kind(file)),
+            ));
+
+            return;
+        };
+
+        let name = match file.get("name") {
+            Some(Value::String(name)) if !name.is_empty() => Some(name.as_str()),
+            Some(Value::String(_)) => {
+                self.issues.push(Issue::file(idx, None, "empty `name`"));
+
+                None
+            }
+            Some(other) => {
+                let msg = format!("`name` must be a string, found {}", kind(other));
+
+                self.issues.push(Issue::file(idx, None, msg));
+
+                None
+            }
+            None => {
+                self.issues.push(Issue::file(idx, None, "missing `name`"));
+
+                None
+            }
+        };
+
+        if let Some(name) = name
+            && let first = *names.entry(name.to_owned()).or_insert(idx)
+            && first != idx
+        {
+            self.issues.push(Issue::file(
+                idx,
+                Some(name),
+                format!("duplicate of source_files[{first}]"),
+            ));
+        }
+
+        let mut issue = |message: String| self.issues.push(Issue::file(idx, name, message));
+
+        match file.get("source_digest") {
+            Some(Value::String(digest)) if !digest.is_empty() => {}
+            Some(Value::String(_)) => issue(String::from("empty `source_digest`")),
+            Some(other) => issue(format!("`source_digest` must be a string, found {}", kind(other))),
+            None => issue(String::from("missing `source_digest`")),
+        }
+
+        let lines = match file.get("coverage") {
+            Some(Value::Array(lines)) => {
+                for (line, hits) in lines.iter().enumerate() {
+                    if let Some(problem) = check_line(hits) {
+                        issue(format!("line {}: {problem}", line + 1));
+                    }
+                }
+
+                Some(lines.len())
+            }
+            Some(other) => {
+                issue(format!("`coverage` must be an array, found {}", kind(other)));
+
+                None
+            }
+            None => {
+                issue(String::from("missing `coverage`"));
+
+                None
+            }
+        };
+
+        check_branches(file, lines, &mut issue);
+    }
+
+    /// Number of source files in the validated report.
+    pub fn files(&self) -> usize {
+        self.files
+    }
+
+    /// The problems found, in the order of the report.
+    pub fn issues(&self) -> &[Issue] {
+        &self.issues
+    }
+
+    /// Tell whether no problem was found.
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Check the hit count of a line, `null` being allowed for the lines irrelevant to coverage.
+fn check_line(hits: &Value) -> Option<String> {
+    match hits {
+        Value::Null => None,
+        Value::Number(_) => check_count(hits),
+        other => Some(format!("expected a count or null, found {}", kind(other))),
+    }
+}
+
+/// Check the `branches` array of a source file having `lines` lines (when known).
+fn check_branches(file: &Map<String, Value>, lines: Option<usize>, issue: &mut impl FnMut(String)) {
+    let branches = match file.get("branches") {
+        None | Some(Value::Null) => return,
+        Some(Value::Array(branches)) => branches,
+        Some(other) => {
+            issue(format!("`branches` must be an array, found {}", kind(other)));
+
+            return;
+        }
+    };
+
+    if branches.len() % 4 != 0 {
+        issue(format!(
+            "`branches` has {} values, not a multiple of 4 (line, block, branch, hits)",
+            branches.len()
+        ));
+    }
+
+    for (idx, value) in branches.iter().enumerate() {
+        if let Some(problem) = check_count(value) {
+            issue(format!("`branches[{idx}]`: {problem}"));
+        }
+    }
+
+    let mut seen = HashMap::new();
+
+    for (idx, branch) in branches.chunks_exact(4).enumerate() {
+        if let (Some(line), Some(lines)) = (branch[0].as_u64(), lines)
+            && (line == 0 || line > lines as u64)
+        {
+            issue(format!(
+                "branch {}: line {line} is out of the {lines} line(s) of the file",
+                idx + 1
+            ));
+        }
+
+        if let (Some(line), Some(block), Some(number)) = (branch[0].as_u64(), branch[1].as_u64(), branch[2].as_u64())
+            && let first = *seen.entry((line, block, number)).or_insert(idx)
+            && first != idx
+        {
+            issue(format!("branch {}: duplicate of branch {}", idx + 1, first + 1));
+        }
+    }
+}
+
+impl fmt::Display for Validation {
+    /// Render the diagnostics, one issue per line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_valid() {
+            return writeln!(f, "The report is valid ({} source file(s))", self.files);
+        }
+
+        writeln!(
+            f,
+            "The report is invalid: {} issue(s) in {} source file(s)",
+            self.issues.len(),
+            self.files
+        )?;
+
+        self.issues.iter().try_for_each(|issue| writeln!(f, "- {issue}"))
+    }
+}
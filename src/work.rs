@@ -1,10 +1,26 @@
-use crate::{cli_args::CliArgs, coverage::Coverage, coveralls::CoverallsManager, config::Config, env::Env};
+use crate::{
+    cli_args::{CliArgs, CliCompareArgs, CliDoctorArgs, CliService, LogFormat},
+    compare::Comparison,
+    coverage::Coverage,
+    coveralls::CoverallsManager,
+    config::{Config, Diagnosis},
+    env::{DotEnv, Env, EnvSnapshot, EnvSource},
+    service::Service,
+    validate::Validation,
+    redact::Redactor,
+    helpers,
+};
+
 use simple_error::SimpleError;
-use log::{debug, info, warn};
+use log::{debug, error, info, warn};
 use clap::Parser;
 use std::{
-    io::{Result, ErrorKind, Error, copy, stdin},
-    fs::File,
+    io::{Result, ErrorKind, Error, BufWriter, Read, Write, stdin, stdout},
+    fs::{File, OpenOptions},
+    ffi::OsString,
+    mem,
+    path::PathBuf,
+    time::Instant,
 };
 
 /// Run the complete `coveralls` workflow, as the command line binary does.
@@ -12,33 +28,133 @@ use std::{
 /// This is the single entry point that ties every stage together:
 ///
 /// 1. parse the command line arguments,
-/// 2. build a [`Config`] from the selected subcommand or, failing that, from the environment,
-/// 3. read the coverage report from the input file or the standard input as a [`Coverage`],
-/// 4. enrich the report and prune unwanted source files through a [`CoverallsManager`],
-/// 5. when `--output` was passed, write the resulting payload to that file,
-/// 6. unless `--no-send` was passed, upload the job to <https://coveralls.io>.
+/// 2. build a [`Config`] from the selected subcommand or, failing that, from the environment (layered
+///    over the dotenv file given with `--env-file` or `COVERALLS_ENV_FILE`, if any, or replaced by the
+///    snapshot given with `--replay`),
+/// 3. stream the coverage report from the input file or the standard input as a [`Coverage`], unwanted
+///    source files being pruned as they are read, those that cannot be parsed diagnosed as by
+///    [`Validation`], and the kept ones spooled to a temporary file,
+/// 4. enrich the report through a [`CoverallsManager`], one source file at a time,
+/// 5. unless `--skip-validation` was passed, check the report against the Coveralls job format (see
+///    [`Validation`]) and fail on any issue,
+/// 6. when `--output` was passed, write the resulting payload to that file,
+/// 7. when `--lcov-output` or `--cobertura-output` was passed, export the processed coverage in that format,
+/// 8. when `--summary-output` or `--step-summary` was passed, render a Markdown summary of the coverage,
+/// 9. unless `--no-send` (or `--replay`) was passed, upload the job to <https://coveralls.io>.
 ///
 /// Logging is performed through the [`log`] crate, so initialize a logger (for instance
-/// [`env_logger`](https://docs.rs/env_logger)) beforehand to see the progress messages.
+/// [`env_logger`](https://docs.rs/env_logger)) beforehand to see the progress messages. The secrets
+/// of the run are collected by a [`Redactor`] of its own: the returned error is redacted with it,
+/// and so are the log records when the logger is wrapped in a [`RedactingLogger`](crate::RedactingLogger).
 ///
 /// # Errors
 ///
 /// Returns an [`std::io::Error`] if no CI service could be determined, if the report cannot be read
-/// or parsed, if the mandatory repository token is missing, or if the upload is rejected by the
-/// Coveralls API.
+/// or parsed, if the mandatory repository token is missing, if the report is invalid, or if the
+/// upload is rejected by the Coveralls API.
 pub fn work() -> Result<()> {
+    work_with_logger(|_| {})
+}
+
+/// Run the complete `coveralls` workflow as [`work`] does, initializing the logging with `init` once
+/// the command line arguments are parsed.
+///
+/// `init` receives the [`LogFormat`] selected with `--log-format`, so the logger is set up from the
+/// same parsed arguments as the rest of the workflow; the `coveralls` binary uses it to write the
+/// log records as JSON objects.
+///
+/// # Errors
+///
+/// Returns an [`std::io::Error`] in the same cases as [`work`].
+pub fn work_with_logger(init: impl FnOnce(LogFormat)) -> Result<()> {
     let args = CliArgs::parse();
+
+    init(args.log_format);
+
     let env = Env::new();
+    let redactor = Redactor::new();
 
-    run(args, env)
+    redactor
+        .scope(|| run(args, env, &redactor))
+        .map_err(|err| redactor.redact_error(err))
+}
+
+/// Run the complete `coveralls` workflow on explicit command line arguments and environment.
+///
+/// This behaves like [`work`], except that `args` (including the program name as first item) are
+/// parsed instead of the process arguments, and the environment variables are read from `env`
+/// instead of the process environment. This lets a tool embedding this crate, or a test, drive the
+/// whole workflow with a fake command line and a fake environment. The logger belongs to the
+/// caller, so `--log-format` is accepted but has no effect.
+///
+/// # Errors
+///
+/// Returns an [`std::io::Error`] if the arguments are invalid (including when `--help` or
+/// `--version` is requested, the error then holding the rendered text), and in the same cases as
+/// [`work`].
+pub fn run_with<I, T>(args: I, env: impl EnvSource + 'static) -> Result<()>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<OsString> + Clone, {
+    let args = match CliArgs::try_parse_from(args) {
+        Ok(v) => v,
+        Err(err) => {
+            return Err(Error::new(ErrorKind::InvalidInput, SimpleError::new(err.render().to_string())));
+        }
+    };
+
+    let redactor = Redactor::new();
+
+    redactor
+        .scope(|| run(args, Env::with_source(env), &redactor))
+        .map_err(|err| redactor.redact_error(err))
 }
 
 /// Run the workflow on already-resolved inputs.
 ///
 /// This is the body of [`work`] with the command line arguments and the environment passed in
-/// rather than read from the process, so the whole workflow can be driven from tests.
-fn run(args: CliArgs, env: Env) -> Result<()> {
-    let do_send = !args.no_send;
+/// rather than read from the process, so the whole workflow can be driven from tests. The secrets of
+/// the run are registered into `redactor`.
+fn run(args: CliArgs, env: Env, redactor: &Redactor) -> Result<()> {
+    let start = Instant::now();
+
+    for pattern in args.redact.iter().flatten() {
+        redactor.add_pattern(pattern)?;
+    }
+
+    match &args.service {
+        CliService::Compare(compare) => return run_compare(&args, compare),
+        CliService::Validate => return run_validate(&args),
+        _ => {}
+    }
+
+    let env = if let Some(path) = &args.replay {
+        info!("Replaying the environment snapshot `{}`", path.display());
+
+        Env::with_source(EnvSnapshot::from_reader(File::open(path)?)?)
+    } else {
+        let env_file = match &args.env_file {
+            Some(path) => Some(path.clone()),
+            None => env.get_var("COVERALLS_ENV_FILE")?.map(PathBuf::from),
+        };
+
+        match env_file {
+            Some(path) => {
+                info!("Reading environment variables from dotenv file `{}`", path.display());
+
+                env.with_fallback(DotEnv::from_path(path)?)
+            }
+            None => env,
+        }
+    };
+
+    match &args.service {
+        CliService::Snapshot => return run_snapshot(&args, env),
+        CliService::Doctor(doctor) => return run_doctor(&args, doctor, env),
+        _ => {}
+    }
+
+    let do_send = !args.no_send && args.replay.is_none();
 
     debug!(
         "Arguments parsed (send: {do_send}, force git fetch: {})",
@@ -59,39 +175,267 @@ fn run(args: CliArgs, env: Env) -> Result<()> {
         }
     };
 
-    info!("Using service `{}`", config.service.get_name());
+    if let Some(token) = &config.repo_token {
+        redactor.add_secret(token);
+    }
+
+    info!(
+        stage = "config",
+        service = config.service.get_name(),
+        repo_token = config.repo_token.as_deref().map(helpers::mask_secret).unwrap_or_default().as_str();
+        "Using service `{}`",
+        config.service.get_name()
+    );
 
     let manager = CoverallsManager::new();
 
-    let mut coverage = if let Some(input) = &args.input {
-        info!("Reading coverage report from file `{}`", input.display());
+    let mut report = read_input(&args, |rdr| manager.read_spooled(&config, rdr), |report| report.sources.entries())?;
+    let apply_start = Instant::now();
 
-        Coverage::from_reader(File::open(input)?)?
-    } else {
-        info!("Reading coverage report from standard input");
+    manager.apply_config_spooled(&config, &mut report, args.force_fetch_git_infos)?;
+    redactor.add_secret(&report.coverage.repo_token);
 
-        Coverage::from_reader(stdin())?
-    };
+    info!(
+        stage = "apply",
+        service = config.service.get_name(),
+        files = report.sources.files(),
+        duration_ms = apply_start.elapsed().as_millis();
+        "Configuration applied to the coverage report"
+    );
+
+    config.show(report.coverage.git());
 
-    manager.apply_config(&config, &mut coverage, args.force_fetch_git_infos)?;
-    config.show(coverage.git());
+    if args.skip_validation {
+        warn!("Validation is disabled (--skip-validation): the report is not checked before use");
+    } else {
+        check(&report.validate()?)?;
+    }
 
     if let Some(output) = &args.output {
         info!("Writing coverage payload to file `{}`", output.display());
 
-        let mut reader = coverage.new_reader()?;
-        let mut file = File::create(output)?;
+        if args.omit_repo_token {
+            let token = mem::take(&mut report.coverage.repo_token);
+            let res = report.write_json(BufWriter::new(File::create(output)?));
+
+            report.coverage.repo_token = token;
+            res?;
+        } else {
+            report.write_json(BufWriter::new(File::create(output)?))?;
+        }
+    }
+
+    // The exports need every source file at once, so they are loaded back from the spool.
+    let exports = [&args.lcov_output, &args.cobertura_output, &args.summary_output];
+    let coverage = if exports.iter().any(|output| output.is_some()) || args.step_summary {
+        report.load_sources()?
+    } else {
+        Coverage::new()
+    };
+
+    if let Some(output) = &args.lcov_output {
+        info!("Writing LCOV report to file `{}`", output.display());
+
+        coverage.write_lcov(BufWriter::new(File::create(output)?))?;
+    }
+
+    if let Some(output) = &args.cobertura_output {
+        info!("Writing Cobertura report to file `{}`", output.display());
 
-        copy(&mut reader, &mut file)?;
+        coverage.write_cobertura(BufWriter::new(File::create(output)?))?;
+    }
+
+    if args.summary_output.is_some() || args.step_summary {
+        let baseline = match &args.summary_baseline {
+            Some(path) => {
+                info!("Reading baseline coverage report from file `{}`", path.display());
+
+                Some(Coverage::from_reader(File::open(path)?)?)
+            }
+            None => None,
+        };
+
+        if let Some(output) = &args.summary_output {
+            info!("Writing Markdown summary to file `{}`", output.display());
+
+            coverage.write_markdown_summary(baseline.as_ref(), BufWriter::new(File::create(output)?))?;
+        }
+
+        if args.step_summary {
+            match env.get_var("GITHUB_STEP_SUMMARY")? {
+                Some(_) if !matches!(config.service, Service::GithubActions | Service::Github) => {
+                    warn!(
+                        "The service `{}` is not GitHub Actions: the job summary is not written",
+                        config.service.get_name()
+                    );
+                }
+                Some(path) => {
+                    info!("Appending Markdown summary to the GitHub Actions job summary");
+
+                    let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+                    coverage.write_markdown_summary(baseline.as_ref(), BufWriter::new(file))?;
+                }
+                None => {
+                    warn!("GITHUB_STEP_SUMMARY is not set: the job summary is not written");
+                }
+            }
+        }
     }
 
     if do_send {
-        manager.send(&coverage)?;
+        manager.send_spooled(&mut report)?;
+    } else if args.replay.is_some() {
+        warn!("Sending is disabled when replaying a snapshot: coverage will not be uploaded to Coveralls");
     } else {
         warn!("Sending is disabled (--no-send): coverage will not be uploaded to Coveralls");
     }
 
-    debug!("Coverage processing finished");
+    info!(
+        stage = "done",
+        service = config.service.get_name(),
+        sent = do_send,
+        duration_ms = start.elapsed().as_millis();
+        "Coverage processing finished"
+    );
+
+    Ok(())
+}
+
+/// Read the coverage report with `parse` from the input file, or from the standard input when there
+/// is none; `entries` counts the source file entries read.
+fn read_input<T>(
+    args: &CliArgs,
+    parse: impl FnOnce(&mut dyn Read) -> Result<T>,
+    entries: impl FnOnce(&T) -> usize,
+) -> Result<T> {
+    let start = Instant::now();
+    let report = if let Some(input) = &args.input {
+        info!("Reading coverage report from file `{}`", input.display());
+
+        parse(&mut File::open(input)?)?
+    } else {
+        info!("Reading coverage report from standard input");
+
+        parse(&mut stdin())?
+    };
+    let files = entries(&report);
+
+    info!(
+        stage = "input",
+        files,
+        duration_ms = start.elapsed().as_millis();
+        "Read {files} source file(s)"
+    );
+
+    Ok(report)
+}
+
+/// Run the `snapshot` subcommand: record the relevant variables of `env` as JSON, into the
+/// `--output` file or to the standard output.
+fn run_snapshot(args: &CliArgs, env: Env) -> Result<()> {
+    let snapshot = EnvSnapshot::capture(env)?;
+
+    info!("Recorded {} environment variable(s)", snapshot.variables().len());
+
+    match &args.output {
+        Some(output) => {
+            info!("Writing environment snapshot to file `{}`", output.display());
+
+            snapshot.to_writer(BufWriter::new(File::create(output)?))
+        }
+        None => snapshot.to_writer(stdout()),
+    }
+}
+
+/// Run the `doctor` subcommand: explain where every configuration value comes from, into the
+/// `--output` file or to the standard output.
+fn run_doctor(args: &CliArgs, doctor: &CliDoctorArgs, env: Env) -> Result<()> {
+    let diagnosis = Diagnosis::new(args, doctor, env)?;
+
+    match &args.output {
+        Some(output) => {
+            info!("Writing configuration diagnosis to file `{}`", output.display());
+
+            let mut file = BufWriter::new(File::create(output)?);
+
+            write!(file, "{diagnosis}")?;
+            file.flush()
+        }
+        None => {
+            print!("{diagnosis}");
+
+            Ok(())
+        }
+    }
+}
+
+/// Fail with the diagnostics of `validation` when the report is invalid, logging every issue.
+fn check(validation: &Validation) -> Result<()> {
+    for issue in validation.issues() {
+        error!(stage = "validate", location = issue.location(); "{}", issue.message());
+    }
+
+    if validation.is_valid() {
+        info!(stage = "validate", files = validation.files(); "The coverage report is valid");
+
+        Ok(())
+    } else {
+        let msg = validation.to_string();
+
+        Err(Error::new(ErrorKind::InvalidData, SimpleError::new(msg.trim_end())))
+    }
+}
+
+/// Run the `validate` subcommand: check the input report, print the diagnostics and fail when it is
+/// invalid.
+///
+/// The input is validated as raw JSON, so that issues preventing its parsing are reported precisely.
+fn run_validate(args: &CliArgs) -> Result<()> {
+    let validation = if let Some(input) = &args.input {
+        info!("Validating coverage report from file `{}`", input.display());
+
+        Validation::from_reader(File::open(input)?)?
+    } else {
+        info!("Validating coverage report from standard input");
+
+        Validation::from_reader(stdin())?
+    };
+
+    print!("{validation}");
+
+    if validation.is_valid() {
+        Ok(())
+    } else {
+        let msg = format!("The coverage report has {} issue(s)", validation.issues().len());
+
+        Err(Error::new(ErrorKind::InvalidData, SimpleError::new(msg)))
+    }
+}
+
+/// Run the `compare` subcommand: print how the input report evolved against the baseline report.
+///
+/// Nothing is sent to Coveralls and no configuration is needed. Fails when `--fail-on-regression`
+/// was passed and the coverage regressed.
+fn run_compare(args: &CliArgs, compare: &CliCompareArgs) -> Result<()> {
+    let coverage = read_input(args, |rdr| Coverage::from_reader(rdr), |coverage| coverage.source_files.len())?;
+
+    info!("Reading baseline coverage report from file `{}`", compare.baseline.display());
+
+    let baseline = Coverage::from_reader(File::open(&compare.baseline)?)?;
+    let comparison = Comparison::new(&baseline, &coverage);
+
+    print!("{comparison}");
+
+    if compare.fail_on_regression && comparison.is_regression(compare.max_decrease) {
+        let msg = format!(
+            "Coverage regressed against the baseline ({:+.2}%, {} line(s) no longer covered)",
+            comparison.delta(),
+            comparison.regressed_lines()
+        );
+
+        return Err(Error::other(SimpleError::new(msg)));
+    }
 
     Ok(())
 }
@@ -99,7 +443,7 @@ fn run(args: CliArgs, env: Env) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::{fs, process};
+    use std::{collections::HashMap, fs, process};
 
     /// End-to-end dry run: read a report, enrich it and write the payload without uploading it.
     ///
@@ -128,7 +472,7 @@ mod tests {
         ])
         .expect("parse the command line arguments");
 
-        let result = run(args, Env::new());
+        let result = run(args, Env::with_source(HashMap::new()), &Redactor::new());
 
         // Clean up before asserting so a failure does not leave the temporary files behind.
         let payload = fs::read_to_string(&output);
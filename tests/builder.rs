@@ -0,0 +1,191 @@
+//! Integration tests for the programmatic construction of a [`Config`] and a [`Coverage`].
+
+use coveralls::{Branch, Config, Coverage, CoverallsManager, MergeMode, Service, SourceFile};
+use std::{io::Read, path::Path};
+
+#[test]
+fn config_builder_sets_the_given_values_only() {
+    let config = Config::builder(Service::Travis)
+        .repo_token("token")
+        .service_job_id("42")
+        .git_branch("main")
+        .prune_dir("target")
+        .prune_dir("vendor")
+        .source_prefix("crate")
+        .build();
+
+    assert_eq!(config.service(), Service::Travis);
+    assert_eq!(config.repo_token(), Some("token"));
+    assert_eq!(config.service_job_id(), Some("42"));
+    assert_eq!(config.git_branch(), Some("main"));
+    assert_eq!(config.flag_name(), None, "nothing is read from the environment");
+    assert_eq!(config.prune_dirs(), [Path::new("target"), Path::new("vendor")]);
+    assert_eq!(config.source_prefix(), Some(Path::new("crate")));
+    assert!(!config.prune_absolutes());
+}
+
+#[test]
+fn built_pipeline_prunes_and_prefixes_source_files() {
+    // The report carries no Git metadata, so it is fetched from this crate's own repository.
+    let config = Config::builder(Service::CircleCI)
+        .repo_token("token")
+        .service_build_number("7")
+        .prune_absolutes(true)
+        .prune_dir("target")
+        .source_prefix("crate")
+        .build();
+
+    let mut coverage = Coverage::new();
+
+    coverage.add_source_file(SourceFile::new("src/lib.rs", "1", vec![Some(1)]));
+    coverage.add_source_file(SourceFile::new("/usr/lib/rust/core.rs", "2", vec![Some(0)]));
+    coverage.add_source_file(SourceFile::new("target/gen.rs", "3", vec![None]));
+
+    CoverallsManager::new()
+        .apply_config(&config, &mut coverage, false)
+        .expect("applying the configuration should succeed");
+
+    let names = coverage.source_files().iter().map(SourceFile::name).collect::<Vec<_>>();
+
+    assert_eq!(names, ["crate/src/lib.rs"]);
+    assert_eq!(coverage.service_name(), "circleci");
+    assert_eq!(coverage.service_number(), "7");
+    assert_eq!(coverage.repo_token(), "token");
+}
+
+#[test]
+fn source_file_mutators_update_the_entry() {
+    let mut source = SourceFile::new("a.rs", "digest", vec![Some(1), None]);
+
+    source.set_name("b.rs");
+    source.set_branches(vec![Branch::new(1, 0, 0, 1)]);
+    source.set_source(Some(String::from("fn main() {}")));
+
+    assert_eq!(source.name(), "b.rs");
+    assert_eq!(source.source_digest(), "digest");
+    assert_eq!(source.coverage(), [Some(1), None]);
+    assert_eq!(source.branches(), [Branch::new(1, 0, 0, 1)]);
+    assert_eq!(source.source(), Some("fn main() {}"));
+}
+
+#[test]
+fn duplicate_source_files_are_merged_after_normalization() {
+    let config = Config::builder(Service::CircleCI)
+        .repo_token("token")
+        .merge_mode(MergeMode::Sum)
+        .build();
+
+    let mut first = SourceFile::new("./src/lib.rs", "1", vec![Some(1), None, Some(0)]);
+    let mut second = SourceFile::new("src/bin/../lib.rs", "1", vec![Some(2), Some(0), Some(0), Some(3)]);
+
+    first.set_branches(vec![Branch::new(1, 0, 0, 1), Branch::new(1, 0, 1, 0)]);
+    second.set_branches(vec![Branch::new(3, 0, 0, 1), Branch::new(1, 0, 1, 2)]);
+
+    let mut coverage = Coverage::new();
+
+    coverage.add_source_file(first);
+    coverage.add_source_file(SourceFile::new("src/main.rs", "2", vec![Some(1)]));
+    coverage.add_source_file(second);
+
+    CoverallsManager::new()
+        .apply_config(&config, &mut coverage, false)
+        .expect("applying the configuration should succeed");
+
+    let names = coverage.source_files().iter().map(SourceFile::name).collect::<Vec<_>>();
+
+    assert_eq!(names, ["src/lib.rs", "src/main.rs"]);
+
+    let merged = &coverage.source_files()[0];
+
+    assert_eq!(merged.coverage(), [Some(3), Some(0), Some(0), Some(3)]);
+    assert_eq!(
+        merged.branches(),
+        [Branch::new(1, 0, 0, 1), Branch::new(1, 0, 1, 2), Branch::new(3, 0, 0, 1)]
+    );
+}
+
+#[test]
+fn merging_keeps_the_highest_counts_by_default() {
+    let mut source = SourceFile::new("a.rs", "digest", vec![Some(1), None, Some(4)]);
+
+    source.merge(
+        SourceFile::new("a.rs", "digest", vec![Some(3), Some(0)]),
+        MergeMode::default(),
+    );
+
+    assert_eq!(source.coverage(), [Some(3), Some(0), Some(4)]);
+}
+
+#[test]
+fn branches_are_normalized_or_dropped() {
+    let json = r#"{"repo_token":"token","source_files":[
+        {"name":"src/a.rs","source_digest":"a","coverage":[1,0],"branches":[2,0,1,0,2,0,0,3,7,0,0,1,2,0,0,1]}
+    ]}"#;
+
+    let manager = CoverallsManager::new();
+    let config = Config::builder(Service::Travis).build();
+    let mut coverage = Coverage::from_reader(json.as_bytes()).unwrap();
+
+    manager.apply_config(&config, &mut coverage, false).unwrap();
+
+    assert_eq!(
+        coverage.source_files()[0].branches(),
+        [Branch::new(2, 0, 0, 3), Branch::new(2, 0, 1, 0)]
+    );
+
+    let mut payload = String::new();
+
+    coverage.new_reader().unwrap().read_to_string(&mut payload).unwrap();
+    assert!(payload.contains(r#""branches":[2,0,0,3,2,0,1,0]"#), "{payload}");
+
+    let config = Config::builder(Service::Travis).drop_branches(true).build();
+    let mut coverage = Coverage::from_reader(json.as_bytes()).unwrap();
+
+    manager.apply_config(&config, &mut coverage, false).unwrap();
+
+    assert!(coverage.source_files()[0].branches().is_empty());
+}
+
+#[test]
+fn branches_must_be_quadruples() {
+    let json = r#"{"source_files":[{"name":"src/a.rs","source_digest":"a","coverage":[1],"branches":[1,0,0]}]}"#;
+    let err = Coverage::from_reader(json.as_bytes()).err().expect("the report should be rejected");
+
+    assert!(
+        err.to_string()
+            .contains("source_files[0] `src/a.rs`: `branches` has 3 values, not a multiple of 4"),
+        "{err}"
+    );
+}
+
+#[test]
+fn job_metadata_is_carried_into_the_payload() {
+    let config = Config::builder(Service::BuildKite)
+        .repo_token("token")
+        .service_build_url("https://ci.example.com/builds/7")
+        .service_job_number("7.2")
+        .service_attempt("3")
+        .compare_ref("main")
+        .git_tag("v1.0.0")
+        .build();
+
+    let mut coverage = Coverage::new();
+
+    CoverallsManager::new()
+        .apply_config(&config, &mut coverage, false)
+        .expect("applying the configuration should succeed");
+
+    let mut payload = String::new();
+
+    coverage.new_reader().unwrap().read_to_string(&mut payload).unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&payload).expect("a valid JSON payload");
+
+    assert_eq!(json["service_build_url"], "https://ci.example.com/builds/7");
+    assert_eq!(json["service_job_number"], "7.2");
+    assert_eq!(json["service_attempt"], "3");
+    assert_eq!(json["compare_ref"], "main");
+    assert_eq!(json["git"]["tag"], "v1.0.0");
+    assert_eq!(json["commit_sha"], json["git"]["head"]["id"]);
+    assert!(json.get("compare_sha").is_none(), "unset fields are skipped");
+}
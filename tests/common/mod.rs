@@ -0,0 +1,52 @@
+//! Helpers shared by the integration tests running the whole workflow, with [`run_with`] or the
+//! `coveralls` binary.
+//!
+//! The Git metadata of these runs is fetched from this crate's own repository (the working directory
+//! of the tests), so the tests using them assume they run from a checkout with `git` available.
+//!
+//! [`run_with`]: coveralls::run_with
+
+// Every test crate only uses some of the helpers.
+#![allow(dead_code)]
+
+use serde_json::Value;
+use std::{
+    fs,
+    io::Result,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Create a fresh temporary directory holding an empty coverage report.
+pub fn fixture(name: &str) -> (PathBuf, PathBuf) {
+    fixture_with(name, r#"{"source_files":[]}"#)
+}
+
+/// Create a fresh temporary directory holding the coverage report `report`.
+pub fn fixture_with(name: &str, report: &str) -> (PathBuf, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("coveralls-test-{name}-{}", process::id()));
+
+    fs::create_dir_all(&dir).expect("create the temporary directory");
+
+    let input = dir.join("coverage.json");
+
+    fs::write(&input, report).expect("write the coverage fixture");
+
+    (dir, input)
+}
+
+/// Parse the content of a JSON file written by a run.
+pub fn parse_json(content: Result<String>) -> Value {
+    serde_json::from_str(&content.expect("the file should have been written")).expect("a valid JSON file")
+}
+
+/// Collect the payload written to `output` by a dry run, once its fixture directory `dir` is removed,
+/// checking that the run succeeded.
+pub fn dry_run_payload(dir: &Path, output: &Path, result: Result<()>) -> Value {
+    let payload = fs::read_to_string(output);
+    fs::remove_dir_all(dir).ok();
+
+    result.expect("the dry-run workflow should succeed");
+
+    parse_json(payload)
+}
@@ -0,0 +1,117 @@
+//! Integration tests for the comparison of a coverage report against a baseline ([`Comparison`]).
+
+use coveralls::{Comparison, Coverage, FileStatus};
+
+fn parse(report: &str) -> Coverage {
+    Coverage::from_reader(report.as_bytes()).expect("parse the report")
+}
+
+#[test]
+fn files_are_aligned_by_name_then_by_digest() {
+    let baseline = parse(
+        r#"{"source_files": [
+            {"name": "src/a.rs", "source_digest": "a", "coverage": [1, 1, null]},
+            {"name": "src/b.rs", "source_digest": "b", "coverage": [1, 0]},
+            {"name": "src/old.rs", "source_digest": "r", "coverage": [1]},
+            {"name": "src/gone.rs", "source_digest": "g", "coverage": [0]}
+        ]}"#,
+    );
+
+    let current = parse(
+        r#"{"source_files": [
+            {"name": "src/a.rs", "source_digest": "a", "coverage": [1, 0, null]},
+            {"name": "src/b.rs", "source_digest": "b2", "coverage": [1, 1, 1]},
+            {"name": "src/new.rs", "source_digest": "r", "coverage": [1]},
+            {"name": "src/added.rs", "source_digest": "n", "coverage": [0, 0]}
+        ]}"#,
+    );
+
+    let comparison = Comparison::new(&baseline, &current);
+    let status = |name: &str| {
+        comparison
+            .files()
+            .iter()
+            .find(|file| file.name() == name)
+            .unwrap_or_else(|| panic!("`{name}` should be compared"))
+    };
+
+    assert_eq!(status("src/a.rs").status(), FileStatus::Unchanged);
+    assert_eq!(status("src/a.rs").regressed_lines(), &[2]);
+    assert_eq!(status("src/a.rs").delta(), Some(-50.0));
+
+    assert_eq!(status("src/b.rs").status(), FileStatus::Modified);
+    assert!(status("src/b.rs").regressed_lines().is_empty());
+
+    assert_eq!(status("src/new.rs").status(), FileStatus::Renamed);
+    assert_eq!(status("src/new.rs").previous_name(), Some("src/old.rs"));
+
+    assert_eq!(status("src/added.rs").status(), FileStatus::Added);
+    assert_eq!(status("src/added.rs").baseline_percent(), None);
+
+    assert_eq!(status("src/gone.rs").status(), FileStatus::Removed);
+    assert_eq!(status("src/gone.rs").current_percent(), None);
+
+    assert_eq!(comparison.files().len(), 5);
+    assert_eq!(comparison.regressed_lines(), 1);
+}
+
+#[test]
+fn regression_gate_honors_the_tolerance() {
+    let baseline = parse(r#"{"source_files": [{"name": "a", "source_digest": "1", "coverage": [1, 1, 1, 1]}]}"#);
+    let current = parse(r#"{"source_files": [{"name": "a", "source_digest": "2", "coverage": [1, 1, 1, 0, 1]}]}"#);
+
+    let comparison = Comparison::new(&baseline, &current);
+
+    assert_eq!(comparison.delta(), -20.0);
+    assert!(comparison.is_regression(10.0));
+    assert!(
+        !comparison.is_regression(25.0),
+        "a modified file has no line-level regression"
+    );
+    assert!(!Comparison::new(&baseline, &baseline).is_regression(0.0));
+}
+
+#[test]
+fn files_sharing_a_digest_are_not_taken_for_renames() {
+    let baseline = parse(
+        r#"{"source_files": [
+            {"name": "src/a/mod.rs", "source_digest": "empty", "coverage": []},
+            {"name": "src/b/mod.rs", "source_digest": "empty", "coverage": []},
+            {"name": "src/x/stub.rs", "source_digest": "s", "coverage": [1]},
+            {"name": "src/y/stub.rs", "source_digest": "s", "coverage": [0]},
+            {"name": "src/old.rs", "source_digest": "r", "coverage": [1]}
+        ]}"#,
+    );
+
+    let current = parse(
+        r#"{"source_files": [
+            {"name": "src/a/mod.rs", "source_digest": "empty", "coverage": []},
+            {"name": "src/c/mod.rs", "source_digest": "empty", "coverage": []},
+            {"name": "src/z/stub.rs", "source_digest": "s", "coverage": [1]},
+            {"name": "src/d.rs", "source_digest": "r", "coverage": [1]},
+            {"name": "src/e.rs", "source_digest": "r", "coverage": [0]}
+        ]}"#,
+    );
+
+    let comparison = Comparison::new(&baseline, &current);
+    let file = |name: &str| {
+        comparison
+            .files()
+            .iter()
+            .find(|file| file.name() == name)
+            .unwrap_or_else(|| panic!("`{name}` should be compared"))
+    };
+
+    assert_eq!(file("src/a/mod.rs").status(), FileStatus::Unchanged);
+
+    assert_eq!(file("src/c/mod.rs").status(), FileStatus::Renamed, "a single leftover on both sides");
+    assert_eq!(file("src/c/mod.rs").previous_name(), Some("src/b/mod.rs"));
+
+    assert_eq!(file("src/z/stub.rs").status(), FileStatus::Added, "two baseline files have the digest");
+    assert_eq!(file("src/x/stub.rs").status(), FileStatus::Removed);
+    assert_eq!(file("src/y/stub.rs").status(), FileStatus::Removed);
+
+    assert_eq!(file("src/d.rs").status(), FileStatus::Added, "two current files have the digest");
+    assert_eq!(file("src/e.rs").status(), FileStatus::Added);
+    assert_eq!(file("src/old.rs").status(), FileStatus::Removed);
+}
@@ -84,3 +84,36 @@ fn invalid_json_is_rejected() {
 
     assert!(result.is_err(), "invalid JSON must be rejected");
 }
+
+#[test]
+fn source_files_are_filtered_while_streaming() {
+    // The `source_files` array comes first, before the fields of the job.
+    let report = r#"{
+        "source_files": [
+            {"name": "src/lib.rs", "source_digest": "1", "coverage": [1]},
+            {"name": "/rustc/core.rs", "source_digest": "2", "coverage": [0]}
+        ],
+        "service_name": "travis-ci"
+    }"#;
+
+    let mut seen = vec![];
+    let coverage = Coverage::from_reader_filtered(report.as_bytes(), |source| {
+        seen.push(source.name().to_string());
+
+        (!source.name().starts_with('/')).then_some(source)
+    })
+    .expect("parse the report");
+
+    assert_eq!(seen, ["src/lib.rs", "/rustc/core.rs"]);
+    assert_eq!(coverage.service_name(), "travis-ci");
+    assert_eq!(coverage.source_files().len(), 1);
+
+    let mut written = vec![];
+
+    coverage.write_json(&mut written).expect("serialize the report");
+    assert_eq!(String::from_utf8(written).unwrap(), payload_of(&coverage));
+
+    let trailing = format!("{report} []");
+
+    assert!(Coverage::from_reader(trailing.as_bytes()).is_err(), "trailing data must be rejected");
+}
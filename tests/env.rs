@@ -1,35 +1,167 @@
-//! Integration test for the public [`Env`] accessor.
+//! Integration tests for the public [`Env`] accessor and its [`EnvSource`]s.
 //!
-//! A single test lives in this binary because it mutates the process environment, which would be
-//! racy if it ran alongside other tests in the same binary.
+//! The variables are read from in-memory sources, so these tests never mutate the process
+//! environment and can run concurrently.
 
-use coveralls::Env;
+use coveralls::{Config, DotEnv, Env, EnvSource, LayeredEnv, Service};
+use std::collections::HashMap;
+
+/// Build an in-memory source from `(name, value)` pairs.
+fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect()
+}
 
 #[test]
 fn empty_variables_are_treated_as_unset() {
-    // SAFETY: this is the only test in this binary, so no other thread reads or writes the
-    // environment concurrently.
-    unsafe {
-        std::env::set_var("COVERALLS_IT_SET", "a-value");
-        std::env::set_var("COVERALLS_IT_EMPTY", "");
-        std::env::remove_var("COVERALLS_IT_MISSING");
-    }
-
-    let env = Env::new();
+    let env = Env::with_source(vars(&[("COVERALLS_IT_SET", "a-value"), ("COVERALLS_IT_EMPTY", "")]));
 
     assert_eq!(
-        env.get_var("COVERALLS_IT_SET").expect("reading a set variable should succeed"),
+        env.get_var("COVERALLS_IT_SET")
+            .expect("reading a set variable should succeed"),
         Some(String::from("a-value")),
     );
 
     assert_eq!(
-        env.get_var("COVERALLS_IT_EMPTY").expect("reading an empty variable should succeed"),
+        env.get_var("COVERALLS_IT_EMPTY")
+            .expect("reading an empty variable should succeed"),
         None,
         "an empty variable must be reported as unset",
     );
 
     assert_eq!(
-        env.get_var("COVERALLS_IT_MISSING").expect("reading a missing variable should succeed"),
+        env.get_var("COVERALLS_IT_MISSING")
+            .expect("reading a missing variable should succeed"),
         None,
     );
 }
+
+#[test]
+fn the_service_is_detected_from_the_given_source() {
+    let env = Env::with_source(vars(&[("BUILDKITE", "true")]));
+
+    assert_eq!(
+        Service::from_env(&env).expect("detection should succeed"),
+        Some(Service::BuildKite)
+    );
+    assert_eq!(
+        Service::from_env(&Env::with_source(vars(&[]))).expect("detection should succeed"),
+        None
+    );
+}
+
+#[test]
+fn the_configuration_is_loaded_from_the_given_source() {
+    let env = Env::with_source(vars(&[
+        ("CIRCLECI", "true"),
+        ("CIRCLE_BRANCH", "feature"),
+        ("CIRCLE_PULL_REQUEST", "https://github.com/owner/repo/pull/12"),
+        ("COVERALLS_REPO_TOKEN", "token"),
+    ]));
+
+    let config = Config::load_from_environment(&env)
+        .expect("loading the configuration should succeed")
+        .expect("CIRCLECI should select a service");
+
+    assert_eq!(config.service(), Service::CircleCI);
+    assert_eq!(config.git_branch(), Some("feature"));
+    assert_eq!(config.service_pull_request(), Some("12"));
+    assert_eq!(config.repo_token(), Some("token"));
+}
+
+#[test]
+fn layers_are_looked_up_in_order() {
+    let env = LayeredEnv::new(vars(&[("A", "top")])).with_fallback(vars(&[("A", "bottom"), ("B", "bottom")]));
+
+    assert_eq!(env.var_os("A").expect("A is set"), "top");
+    assert_eq!(env.var_os("B").expect("B is set"), "bottom");
+    assert_eq!(env.var_os("C"), None);
+}
+
+#[test]
+fn dotenv_files_are_parsed() {
+    let content = "\
+        # A CI snapshot\n\
+        export CI_NAME=circleci\n\
+        CI_BRANCH = main # trailing comment\n\
+        CI_AUTHOR_NAME='Ada # Lovelace'\n\
+        GIT_MESSAGE=\"Fix \\\"quotes\\\"\\nand lines\"\n\
+        \n\
+    ";
+
+    let env = DotEnv::parse(content).expect("parse the dotenv file");
+
+    assert_eq!(env.len(), 4);
+    assert_eq!(env.var_os("CI_NAME").expect("CI_NAME is set"), "circleci");
+    assert_eq!(env.var_os("CI_BRANCH").expect("CI_BRANCH is set"), "main");
+    assert_eq!(
+        env.var_os("CI_AUTHOR_NAME").expect("CI_AUTHOR_NAME is set"),
+        "Ada # Lovelace"
+    );
+    assert_eq!(
+        env.var_os("GIT_MESSAGE").expect("GIT_MESSAGE is set"),
+        "Fix \"quotes\"\nand lines"
+    );
+}
+
+#[test]
+fn invalid_dotenv_lines_are_rejected() {
+    let err = DotEnv::parse("A=1\nnot a definition\n").expect_err("the second line is invalid");
+
+    assert!(err.to_string().contains("Line 2"), "{err}");
+    assert!(DotEnv::parse("A='unterminated").is_err());
+    assert!(DotEnv::parse("BAD-NAME=1").is_err());
+}
+
+#[test]
+fn the_github_actions_event_payload_gives_the_pull_request_head() {
+    let dir = std::env::temp_dir().join(format!("coveralls-github-event-{}", std::process::id()));
+
+    std::fs::create_dir_all(&dir).expect("create the temporary directory");
+
+    let event = dir.join("event.json");
+
+    std::fs::write(
+        &event,
+        r#"{
+            "action": "synchronize",
+            "number": 42,
+            "pull_request": {
+                "number": 42,
+                "head": {"ref": "feature", "sha": "1111111111111111111111111111111111111111"},
+                "base": {"ref": "main", "sha": "2222222222222222222222222222222222222222"}
+            }
+        }"#,
+    )
+    .expect("write the event payload");
+
+    let env = Env::with_source(vars(&[
+        ("GITHUB_ACTIONS", "true"),
+        ("GITHUB_REF", "refs/pull/42/merge"),
+        ("GITHUB_SHA", "3333333333333333333333333333333333333333"),
+        ("GITHUB_EVENT_PATH", event.to_str().unwrap()),
+        ("GITHUB_SERVER_URL", "https://github.com"),
+        ("GITHUB_REPOSITORY", "owner/repo"),
+        ("GITHUB_RUN_ID", "987"),
+        ("GITHUB_RUN_ATTEMPT", "2"),
+    ]));
+
+    let config = Config::load_from_environment(&env);
+
+    std::fs::remove_dir_all(&dir).ok();
+
+    let config = config
+        .expect("loading the configuration should succeed")
+        .expect("GITHUB_ACTIONS should select a service");
+
+    assert_eq!(config.service(), Service::GithubActions);
+    assert_eq!(config.service_pull_request(), Some("42"));
+    assert_eq!(config.git_id(), Some("1111111111111111111111111111111111111111"));
+    assert_eq!(config.git_branch(), Some("feature"));
+    assert_eq!(config.compare_ref(), Some("main"));
+    assert_eq!(config.compare_sha(), Some("2222222222222222222222222222222222222222"));
+    assert_eq!(config.service_build_url(), Some("https://github.com/owner/repo/actions/runs/987"));
+    assert_eq!(config.service_attempt(), Some("2"));
+}
@@ -0,0 +1,108 @@
+//! Integration tests for the LCOV and Cobertura writers of [`Coverage`].
+
+use coveralls::Coverage;
+
+/// A report with one partially covered file and one branch pair on its fourth line.
+const REPORT: &str = r#"{
+    "source_files": [
+        {
+            "name": "src/lib.rs",
+            "source_digest": "deadbeef",
+            "coverage": [1, null, 0, 3],
+            "branches": [4, 0, 0, 2, 4, 0, 1, 0]
+        },
+        {"name": "main.rs", "source_digest": "cafe", "coverage": [null, 2]}
+    ]
+}"#;
+
+#[test]
+fn lcov_lists_lines_branches_and_totals() {
+    let coverage = Coverage::from_reader(REPORT.as_bytes()).expect("parse the report");
+    let mut out = vec![];
+
+    coverage.write_lcov(&mut out).expect("write the LCOV report");
+
+    let lcov = String::from_utf8(out).expect("an UTF-8 report");
+    let expected = "\
+        TN:\n\
+        SF:src/lib.rs\nDA:1,1\nDA:3,0\nDA:4,3\nBRDA:4,0,0,2\nBRDA:4,0,1,0\nBRF:2\nBRH:1\nLF:3\nLH:2\nend_of_record\n\
+        SF:main.rs\nDA:2,2\nLF:1\nLH:1\nend_of_record\n\
+    ";
+
+    assert_eq!(lcov, expected);
+}
+
+#[test]
+fn cobertura_groups_files_by_package() {
+    let coverage = Coverage::from_reader(REPORT.as_bytes()).expect("parse the report");
+    let mut out = vec![];
+
+    coverage.write_cobertura(&mut out).expect("write the Cobertura report");
+
+    let xml = String::from_utf8(out).expect("an UTF-8 report");
+
+    assert!(xml.contains(r#"lines-covered="3" lines-valid="4""#), "{xml}");
+    assert!(xml.contains(r#"branches-covered="1" branches-valid="2""#), "{xml}");
+    assert!(xml.contains(r#"<package name="src" line-rate="0.6667""#), "{xml}");
+    assert!(xml.contains(r#"<package name="." line-rate="1.0000""#), "{xml}");
+    assert!(xml.contains(r#"<class name="lib.rs" filename="src/lib.rs""#), "{xml}");
+    assert!(
+        xml.contains(r#"<line number="4" hits="3" branch="true" condition-coverage="50% (1/2)"/>"#),
+        "{xml}"
+    );
+
+    assert!(xml.contains(r#"<line number="3" hits="0" branch="false"/>"#), "{xml}");
+}
+
+#[test]
+fn cobertura_leaves_out_the_branches_of_lines_without_element() {
+    let report = r#"{"source_files": [{
+        "name": "src/lib.rs",
+        "source_digest": "deadbeef",
+        "coverage": [1, null],
+        "branches": [1, 0, 0, 1, 2, 0, 0, 1, 3, 0, 0, 0]
+    }]}"#;
+    let coverage = Coverage::from_reader(report.as_bytes()).expect("parse the report");
+    let mut out = vec![];
+
+    coverage.write_cobertura(&mut out).expect("write the Cobertura report");
+
+    let xml = String::from_utf8(out).expect("an UTF-8 report");
+
+    assert!(xml.contains(r#"branches-covered="1" branches-valid="1""#), "{xml}");
+    assert!(
+        xml.contains(r#"<line number="1" hits="1" branch="true" condition-coverage="100% (1/1)"/>"#),
+        "{xml}"
+    );
+    assert!(!xml.contains(r#"<line number="2""#), "{xml}");
+    assert!(!xml.contains(r#"<line number="3""#), "{xml}");
+}
+
+#[test]
+fn markdown_summary_reports_totals_changes_and_uncovered_files() {
+    let coverage = Coverage::from_reader(REPORT.as_bytes()).expect("parse the report");
+    let baseline = Coverage::from_reader(
+        r#"{"source_files": [{"name": "src/lib.rs", "source_digest": "old", "coverage": [1, 0]}]}"#.as_bytes(),
+    )
+    .expect("parse the baseline");
+
+    let mut out = vec![];
+
+    coverage
+        .write_markdown_summary(Some(&baseline), &mut out)
+        .expect("write the Markdown summary");
+
+    let md = String::from_utf8(out).expect("an UTF-8 summary");
+
+    assert!(md.contains("| **Total** | 4 | 3 | **75.00%** | +25.00% |"), "{md}");
+    assert!(md.contains("Branches: 1 of 2 covered (50.00%)."), "{md}");
+    assert!(md.contains("| `src/lib.rs` | 3 | 2 | 66.67% | +16.67% |"), "{md}");
+    assert!(md.contains("| `main.rs` | 1 | 1 | 100.00% | new |"), "{md}");
+    let top = md
+        .split("### Top uncovered files")
+        .nth(1)
+        .expect("a top uncovered files section");
+
+    assert!(top.contains("| `src/lib.rs` | 1 | 66.67% |"), "{md}");
+    assert!(!top.contains("main.rs"), "fully covered files are not listed: {md}");
+}
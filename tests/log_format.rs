@@ -0,0 +1,52 @@
+//! Integration tests of the `--log-format` option of the `coveralls` binary.
+//!
+//! The binary is run as a subprocess, since the logger is set up by `main`.
+
+mod common;
+
+use common::fixture_with;
+use std::{fs, process::Command};
+
+#[test]
+fn json_log_records_carry_structured_fields_without_the_token() {
+    let (dir, input) = fixture_with(
+        "log-format",
+        r#"{"source_files":[
+            {"name":"/abs/dep.rs","source_digest":"a","coverage":[1]},
+            {"name":"src/lib.rs","source_digest":"b","coverage":[null,0]}
+        ]}"#,
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_coveralls"))
+        .args(["--log-format", "json", "--no-send", "--prune-absolutes"])
+        .arg(&input)
+        .arg("circleci")
+        .env("RUST_LOG", "info")
+        .env("COVERALLS_REPO_TOKEN", "secret-token-1234")
+        .output();
+
+    fs::remove_dir_all(&dir).ok();
+
+    let output = output.expect("run the binary");
+    let stderr = String::from_utf8(output.stderr).expect("UTF-8 logs");
+
+    assert!(output.status.success(), "{stderr}");
+    assert!(!stderr.contains("secret-token-1234"), "the token is masked");
+
+    let records = stderr
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).expect("one JSON object per line"))
+        .collect::<Vec<_>>();
+
+    let stage = |name: &str| {
+        records
+            .iter()
+            .find(|record| record["stage"] == name)
+            .unwrap_or_else(|| panic!("no `{name}` event in {stderr}"))
+    };
+
+    assert_eq!(stage("config")["service"], "circleci");
+    assert_eq!(stage("prune")["kept"], 1);
+    assert_eq!(stage("prune")["pruned"], 1);
+    assert!(stage("done")["duration_ms"].is_u64());
+}
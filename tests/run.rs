@@ -0,0 +1,335 @@
+//! Integration tests of the [`run_with`] entry point, driven by a fake command line and a fake
+//! environment.
+//!
+//! Unlike the other workflow tests, these never touch the process environment, so they can run
+//! concurrently.
+
+mod common;
+
+use common::{dry_run_payload, fixture, fixture_with, parse_json};
+use coveralls::run_with;
+use std::{collections::HashMap, fs};
+
+#[test]
+fn the_service_is_guessed_from_the_given_environment() {
+    let (dir, input) = fixture("env");
+    let output = dir.join("payload.json");
+    let env = HashMap::from([
+        (String::from("CI_NAME"), String::from("travis-ci")),
+        (String::from("CI_JOB_ID"), String::from("1234")),
+        (String::from("COVERALLS_REPO_TOKEN"), String::from("fake-token")),
+    ]);
+
+    let result = run_with(
+        [
+            "coveralls",
+            "--no-send",
+            "--output",
+            output.to_str().unwrap(),
+            input.to_str().unwrap(),
+            "env",
+        ],
+        env,
+    );
+
+    let json = dry_run_payload(&dir, &output, result);
+
+    assert_eq!(json["service_name"], "travis-ci");
+    assert_eq!(json["service_job_id"], "1234");
+    assert_eq!(json["repo_token"], "fake-token");
+}
+
+#[test]
+fn the_source_files_are_pruned_merged_and_normalized_on_the_way_to_the_payload() {
+    let (dir, input) = fixture_with(
+        "spool",
+        r#"{"source_files":[
+            {"name":"./src/lib.rs","source_digest":"1","coverage":[1,0],"branches":[1,0,0,1,3,0,0,1]},
+            {"name":"/usr/lib/rust/core.rs","source_digest":"2","coverage":[0]},
+            {"name":"src/main.rs","source_digest":"3","coverage":[null,4]},
+            {"name":"src/lib.rs","source_digest":"1","coverage":[2,1],"branches":[1,0,0,2]}
+        ]}"#,
+    );
+    let output = dir.join("payload.json");
+    let lcov = dir.join("coverage.lcov");
+    let env = HashMap::from([
+        (String::from("CI_NAME"), String::from("travis-ci")),
+        (String::from("COVERALLS_REPO_TOKEN"), String::from("fake-token")),
+    ]);
+
+    let result = run_with(
+        [
+            "coveralls",
+            "--no-send",
+            "--prune-absolutes",
+            "--source-prefix",
+            "crate",
+            "--merge-mode",
+            "sum",
+            "--output",
+            output.to_str().unwrap(),
+            "--lcov-output",
+            lcov.to_str().unwrap(),
+            input.to_str().unwrap(),
+            "env",
+        ],
+        env,
+    );
+
+    let lcov = fs::read_to_string(lcov);
+    let json = dry_run_payload(&dir, &output, result);
+    let files = json["source_files"].as_array().expect("an array of source files");
+
+    assert_eq!(files.len(), 2, "{json}");
+    assert_eq!(files[0]["name"], "crate/src/lib.rs");
+    assert_eq!(files[0]["coverage"], serde_json::json!([3, 1]));
+    assert_eq!(files[0]["branches"], serde_json::json!([1, 0, 0, 3]));
+    assert_eq!(files[1]["name"], "crate/src/main.rs");
+    assert!(lcov.expect("the LCOV report should have been written").contains("SF:crate/src/lib.rs\nDA:1,3\n"));
+}
+
+#[test]
+fn a_missing_token_in_the_given_environment_is_an_error() {
+    let (dir, input) = fixture("no-token");
+    let result = run_with(
+        ["coveralls", "--no-send", input.to_str().unwrap(), "circleci"],
+        HashMap::new(),
+    );
+
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(result.is_err(), "the repository token is mandatory");
+}
+
+#[test]
+fn invalid_arguments_are_reported_as_errors() {
+    assert!(run_with(["coveralls", "--no-such-option", "env"], HashMap::new()).is_err());
+}
+
+#[test]
+fn a_dotenv_file_is_layered_under_the_environment() {
+    let (dir, input) = fixture("dotenv");
+    let output = dir.join("payload.json");
+    let env_file = dir.join("ci.env");
+
+    fs::write(
+        &env_file,
+        "CI_NAME=circleci\nCI_JOB_ID=from-file\nCOVERALLS_REPO_TOKEN=file-token\n",
+    )
+    .expect("write the dotenv fixture");
+
+    let env = HashMap::from([
+        (
+            String::from("COVERALLS_ENV_FILE"),
+            env_file.to_str().unwrap().to_string(),
+        ),
+        (String::from("CI_JOB_ID"), String::from("from-env")),
+    ]);
+
+    let result = run_with(
+        [
+            "coveralls",
+            "--no-send",
+            "--output",
+            output.to_str().unwrap(),
+            input.to_str().unwrap(),
+            "env",
+        ],
+        env,
+    );
+
+    let json = dry_run_payload(&dir, &output, result);
+
+    assert_eq!(json["service_name"], "circleci");
+    assert_eq!(json["service_job_id"], "from-env", "the environment takes precedence");
+    assert_eq!(json["repo_token"], "file-token");
+}
+
+#[test]
+fn a_snapshot_masks_secrets_and_can_be_replayed() {
+    let (dir, input) = fixture("snapshot");
+    let snapshot = dir.join("snapshot.json");
+    let output = dir.join("payload.json");
+    let env = HashMap::from([
+        (String::from("CIRCLECI"), String::from("true")),
+        (String::from("CIRCLE_BRANCH"), String::from("feature")),
+        (String::from("CIRCLE_BUILD_NUM"), String::from("42")),
+        (String::from("COVERALLS_REPO_TOKEN"), String::from("secret-token")),
+        (String::from("UNRELATED"), String::from("ignored")),
+    ]);
+
+    let captured = run_with(["coveralls", "--output", snapshot.to_str().unwrap(), "snapshot"], env);
+    let content = fs::read_to_string(&snapshot);
+    let replayed = run_with(
+        [
+            "coveralls",
+            "--replay",
+            snapshot.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            input.to_str().unwrap(),
+            "env",
+        ],
+        HashMap::new(),
+    );
+    let payload = fs::read_to_string(&output);
+    fs::remove_dir_all(&dir).ok();
+
+    captured.expect("the snapshot should succeed");
+    replayed.expect("the replay should succeed, without sending anything");
+
+    let json = parse_json(content);
+
+    assert_eq!(json["variables"]["CIRCLE_BRANCH"], "feature");
+    assert_eq!(json["variables"].get("UNRELATED"), None);
+    assert_ne!(json["variables"]["COVERALLS_REPO_TOKEN"], "secret-token");
+    assert_eq!(json["masked"][0], "COVERALLS_REPO_TOKEN");
+
+    let json = parse_json(payload);
+
+    assert_eq!(json["service_name"], "circleci");
+    assert_eq!(json["service_number"], "42");
+}
+
+#[test]
+fn a_snapshot_embeds_the_pull_request_of_the_github_event() {
+    let (dir, input) = fixture("snapshot-event");
+    let event = dir.join("event.json");
+    let snapshot = dir.join("snapshot.json");
+    let output = dir.join("payload.json");
+
+    fs::write(
+        &event,
+        r#"{
+            "pull_request": {
+                "number": 7,
+                "title": "Not recorded",
+                "head": {"ref": "fix-secret-token", "sha": "1111111111111111111111111111111111111111"},
+                "base": {"ref": "main", "sha": "2222222222222222222222222222222222222222"}
+            }
+        }"#,
+    )
+    .expect("write the event payload");
+
+    let env = HashMap::from([
+        (String::from("GITHUB_ACTIONS"), String::from("true")),
+        (String::from("GITHUB_REF"), String::from("refs/pull/7/merge")),
+        (String::from("GITHUB_SHA"), String::from("3333333333333333333333333333333333333333")),
+        (String::from("GITHUB_EVENT_PATH"), event.to_str().unwrap().to_string()),
+        (String::from("COVERALLS_REPO_TOKEN"), String::from("secret-token")),
+    ]);
+
+    let captured = run_with(["coveralls", "--output", snapshot.to_str().unwrap(), "snapshot"], env);
+
+    // The replay happens elsewhere, without the event payload.
+    fs::remove_file(&event).ok();
+
+    let replayed = run_with(
+        [
+            "coveralls",
+            "--replay",
+            snapshot.to_str().unwrap(),
+            "--output",
+            output.to_str().unwrap(),
+            input.to_str().unwrap(),
+            "env",
+        ],
+        HashMap::new(),
+    );
+    let content = fs::read_to_string(&snapshot);
+    let payload = fs::read_to_string(&output);
+    fs::remove_dir_all(&dir).ok();
+
+    captured.expect("the snapshot should succeed");
+    replayed.expect("the replay should succeed, without sending anything");
+
+    let content = content.expect("the snapshot should have been written");
+    let json: serde_json::Value = serde_json::from_str(&content).expect("a valid JSON snapshot");
+    let pull_request = &json["github_event"]["pull_request"];
+
+    assert_eq!(pull_request["number"], 7);
+    assert_eq!(pull_request["base"]["sha"], "2222222222222222222222222222222222222222");
+    assert_eq!(pull_request.get("title"), None, "only the pull request subset is embedded");
+    assert!(!content.contains("secret-token"), "the secrets are masked: {content}");
+
+    let json = parse_json(payload);
+
+    assert_eq!(json["service_pull_request"], "7");
+    assert_eq!(json["git"]["head"]["id"], "1111111111111111111111111111111111111111");
+    assert_eq!(json["git"]["branch"], "fix-****oken");
+}
+
+#[test]
+fn the_doctor_explains_where_values_come_from() {
+    let (dir, _) = fixture("doctor");
+    let output = dir.join("doctor.txt");
+    let env = HashMap::from([
+        (String::from("CI_NAME"), String::from("travis-ci")),
+        (String::from("CI_JOB_ID"), String::from("1234")),
+        (String::from("CI_PULL_REQUEST"), String::from("56")),
+        (String::from("COVERALLS_REPO_TOKEN"), String::from("secret-token")),
+    ]);
+
+    let result = run_with(
+        ["coveralls", "--output", output.to_str().unwrap(), "doctor", "--flag-name", "unit"],
+        env,
+    );
+
+    let report = fs::read_to_string(&output);
+    fs::remove_dir_all(&dir).ok();
+
+    result.expect("the doctor should succeed");
+
+    let report = report.expect("the diagnosis should have been written");
+
+    assert!(report.contains("Detected by: CI_NAME=`travis-ci`"), "{report}");
+    assert!(report.contains("service_job_id         [1234] (environment variable `CI_JOB_ID`)"), "{report}");
+    assert!(report.contains("flag_name              [unit] (flag `--flag-name`)"), "{report}");
+    assert!(report.contains("git_author_date        [20"), "{report}");
+    assert!(!report.contains("secret-token"), "the token is masked");
+}
+
+#[test]
+fn the_github_service_authenticates_with_the_workflow_token() {
+    let (dir, input) = fixture("github");
+    let output = dir.join("payload.json");
+    let env = HashMap::from([
+        (String::from("GITHUB_ACTIONS"), String::from("true")),
+        (String::from("GITHUB_TOKEN"), String::from("ghs_workflow-token")),
+        (String::from("GITHUB_JOB"), String::from("test")),
+        (String::from("GITHUB_RUN_ID"), String::from("5678")),
+    ]);
+
+    let result = run_with(
+        [
+            "coveralls",
+            "--no-send",
+            "--output",
+            output.to_str().unwrap(),
+            input.to_str().unwrap(),
+            "github",
+        ],
+        env,
+    );
+
+    let json = dry_run_payload(&dir, &output, result);
+
+    assert_eq!(json["service_name"], "github");
+    assert_eq!(json["repo_token"], "ghs_workflow-token");
+    assert_eq!(json["service_job_id"], "5678");
+}
+
+#[test]
+fn the_github_service_without_any_token_is_an_error() {
+    let (dir, input) = fixture("github-no-token");
+    let result = run_with(
+        ["coveralls", "--no-send", input.to_str().unwrap(), "github"],
+        HashMap::new(),
+    );
+
+    fs::remove_dir_all(&dir).ok();
+
+    let err = result.expect_err("a token is still mandatory");
+
+    assert!(err.to_string().contains("GITHUB_TOKEN"), "{err}");
+}
@@ -11,6 +11,7 @@ fn known_service_names_round_trip() {
         "jenkins",
         "semaphore-ci",
         "github-actions",
+        "github",
         "buildkite",
     ];
 
@@ -0,0 +1,97 @@
+//! Integration tests of the report validation, standalone and as a stage of the workflow.
+
+mod common;
+
+use common::fixture_with;
+use coveralls::{Validation, run_with};
+use std::{collections::HashMap, fs};
+
+#[test]
+fn structural_issues_are_reported_with_the_file() {
+    let json = r#"{"source_files":[
+        {"name":"src/a.rs","source_digest":"a","coverage":[1,null,-3],"branches":[1,0,0,2,2]},
+        {"name":"","source_digest":"b","coverage":[]},
+        {"name":"src/a.rs","source_digest":"","coverage":"oops"},
+        {"name":"src/b.rs","source_digest":"c","coverage":[0],"branches":[4,0,0,1,4,0,0,2]}
+    ]}"#;
+
+    let validation = Validation::from_reader(json.as_bytes()).expect("a JSON document");
+    let issues = validation.issues().iter().map(ToString::to_string).collect::<Vec<_>>();
+
+    assert_eq!(
+        issues,
+        [
+            "source_files[0] `src/a.rs`: line 3: negative count -3",
+            "source_files[0] `src/a.rs`: `branches` has 5 values, not a multiple of 4 (line, block, branch, hits)",
+            "source_files[1]: empty `name`",
+            "source_files[2] `src/a.rs`: duplicate of source_files[0]",
+            "source_files[2] `src/a.rs`: empty `source_digest`",
+            "source_files[2] `src/a.rs`: `coverage` must be an array, found a string",
+            "source_files[3] `src/b.rs`: branch 1: line 4 is out of the 1 line(s) of the file",
+            "source_files[3] `src/b.rs`: branch 2: line 4 is out of the 1 line(s) of the file",
+            "source_files[3] `src/b.rs`: branch 2: duplicate of branch 1",
+        ]
+    );
+
+    assert_eq!(validation.files(), 4);
+}
+
+#[test]
+fn a_well_formed_report_is_valid() {
+    let json =
+        r#"{"source_files":[{"name":"src/a.rs","source_digest":"a","coverage":[1,null,0],"branches":[1,0,0,2]}]}"#;
+    let validation = Validation::from_reader(json.as_bytes()).expect("a JSON document");
+
+    assert!(validation.is_valid(), "{validation}");
+}
+
+#[test]
+fn the_workflow_rejects_an_invalid_report_unless_skipped() {
+    let (dir, input) = fixture_with(
+        "validate",
+        r#"{"source_files":[{"name":"src/a.rs","source_digest":"","coverage":[1],"branches":[1,0,0,1]}]}"#,
+    );
+
+    let env = || HashMap::from([(String::from("COVERALLS_REPO_TOKEN"), String::from("token"))]);
+    let input = input.to_str().unwrap();
+
+    let standalone = run_with(["coveralls", input, "validate"], HashMap::new());
+    let rejected = run_with(["coveralls", "--no-send", input, "circleci"], env());
+    let skipped = run_with(
+        ["coveralls", "--no-send", "--skip-validation", input, "circleci"],
+        env(),
+    );
+
+    fs::remove_dir_all(&dir).ok();
+
+    assert!(standalone.is_err(), "the standalone validation fails");
+
+    let err = rejected
+        .expect_err("the invalid report is rejected before upload")
+        .to_string();
+
+    assert!(err.contains("empty `source_digest`"), "{err}");
+
+    skipped.expect("the validation can be skipped");
+}
+
+#[test]
+fn the_workflow_names_the_source_files_it_cannot_parse() {
+    let (dir, input) = fixture_with(
+        "validate-unparsable",
+        r#"{"source_files":[
+            {"name":"src/a.rs","source_digest":"a","coverage":[1]},
+            {"name":"src/b.rs","source_digest":"b","coverage":[1,-3]}
+        ]}"#,
+    );
+
+    let env = HashMap::from([(String::from("COVERALLS_REPO_TOKEN"), String::from("token"))]);
+    let result = run_with(["coveralls", "--no-send", input.to_str().unwrap(), "circleci"], env);
+
+    fs::remove_dir_all(&dir).ok();
+
+    let err = result.expect_err("the report cannot be parsed").to_string();
+
+    assert!(err.contains("1 issue(s) in 2 source file(s)"), "{err}");
+    assert!(err.contains("source_files[1] `src/b.rs`: line 2: negative count -3"), "{err}");
+}
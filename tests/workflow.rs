@@ -4,26 +4,20 @@
 //! environment, parse a [`Coverage`] report, enrich it with a [`CoverallsManager`] and serialize
 //! the resulting payload — all without uploading anything.
 //!
-//! The configuration is driven through environment variables, so this binary deliberately contains
-//! a single test: integration test binaries run their tests on several threads, and mutating the
-//! process environment from more than one test at a time would be racy. The Git metadata is fetched
-//! from this crate's own repository (the working directory of the test), so the test assumes it runs
-//! from a checkout with `git` available.
+//! The configuration is driven through an in-memory environment, so the process environment is
+//! left untouched. The Git metadata is fetched from this crate's own repository (the working
+//! directory of the test), so the test assumes it runs from a checkout with `git` available.
 
 use coveralls::{Config, Coverage, CoverallsManager, Env};
-use std::io::Read;
+use std::{collections::HashMap, io::Read};
 
 #[test]
 fn library_workflow_enriches_and_serializes_the_report() {
-    // SAFETY: this is the only test in this binary, so no other thread accesses the environment
-    // concurrently. `CI_NAME` is the first source checked by `load_from_environment`, so it selects
-    // the service regardless of the ambient CI variables.
-    unsafe {
-        std::env::set_var("CI_NAME", "circleci");
-        std::env::set_var("COVERALLS_REPO_TOKEN", "integration-token");
-    }
-
-    let env = Env::new();
+    let env = Env::with_source(HashMap::from([
+        (String::from("CI_NAME"), String::from("circleci")),
+        (String::from("COVERALLS_REPO_TOKEN"), String::from("integration-token")),
+    ]));
+
     let config = Config::load_from_environment(&env)
         .expect("loading the configuration should succeed")
         .expect("CI_NAME should select a service");